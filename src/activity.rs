@@ -0,0 +1,262 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use serde::Serialize;
+
+use crate::cli;
+use crate::config::expand_tilde;
+use crate::staging::StageStats;
+
+/// Something `stage --continuous` did, reported as one line
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Activity {
+    Started {
+        project: String,
+        roots: Vec<String>,
+    },
+    Staged {
+        title: String,
+        source: String,
+        reason: String,
+        written: usize,
+        unchanged: usize,
+        skipped: usize,
+    },
+    Removed {
+        source: String,
+        files: usize,
+    },
+    /// A restage went to a new target and the previous output was deleted
+    Replaced {
+        title: String,
+        files: usize,
+    },
+    Warning {
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+    /// Shutdown was requested; changes still waiting out the quiet period are staged first
+    Stopping {
+        pending: usize,
+    },
+    Stopped {
+        duration_secs: u64,
+        staged: usize,
+        files_written: usize,
+        removed: usize,
+        warnings: usize,
+        errors: usize,
+    },
+}
+
+impl Activity {
+    pub fn staged(title: &str, source: &str, reason: &str, stats: StageStats) -> Self {
+        Activity::Staged {
+            title: title.to_string(),
+            source: source.to_string(),
+            reason: reason.to_string(),
+            written: stats.written,
+            unchanged: stats.unchanged,
+            skipped: stats.skipped,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Activity::Warning {
+            message: message.into(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Activity::Error {
+            message: message.into(),
+        }
+    }
+
+    fn level(&self) -> &'static str {
+        match self {
+            Activity::Staged { .. } | Activity::Removed { .. } | Activity::Replaced { .. } => "OK",
+            Activity::Warning { .. } => "WARN",
+            Activity::Error { .. } => "ERR",
+            _ => "INFO",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Activity::Started { project, roots } => {
+                format!("Watching project '{}' in: {}", project, roots.join(", "))
+            }
+            Activity::Staged {
+                title,
+                reason,
+                written,
+                unchanged,
+                skipped,
+                ..
+            } => format!(
+                "Staged {} ({}): {} written / {} unchanged / {} skipped",
+                title, reason, written, unchanged, skipped
+            ),
+            Activity::Removed { source, files } => {
+                format!("Removed {} staged file(s) of deleted {}", files, source)
+            }
+            Activity::Replaced { title, files } => {
+                format!("Removed {} file(s) of the previous output of {}", files, title)
+            }
+            Activity::Warning { message } | Activity::Error { message } => message.clone(),
+            Activity::Stopping { pending } => {
+                format!("Stopping, staging {} pending change(s) first...", pending)
+            }
+            Activity::Stopped {
+                duration_secs,
+                staged,
+                removed,
+                errors,
+                ..
+            } => format!(
+                "Stopped after {}: {} staged, {} removed, {} error(s)",
+                format_duration(Duration::from_secs(*duration_secs)),
+                staged,
+                removed,
+                errors
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    time: &'a str,
+    #[serde(flatten)]
+    activity: &'a Activity,
+}
+
+/// Counts kept over one watch session for the summary on exit
+#[derive(Debug, Default)]
+struct SessionTotals {
+    staged: usize,
+    files_written: usize,
+    removed: usize,
+    warnings: usize,
+    errors: usize,
+}
+
+/// Reports the activity of a watch session to the terminal, or as JSON lines
+/// on stdout, and appends it to an optional log file
+pub struct ActivityLog {
+    json: bool,
+    file: Option<File>,
+    started: Instant,
+    totals: SessionTotals,
+}
+
+impl ActivityLog {
+    pub fn new(json: bool, log_file: Option<&str>) -> Result<Self, String> {
+        let file = match log_file {
+            Some(path) => {
+                let path = expand_tilde(path);
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| format!("Cannot open log file {}: {}", path, e))?;
+                Some(file)
+            }
+            None => None,
+        };
+        Ok(ActivityLog {
+            json,
+            file,
+            started: Instant::now(),
+            totals: SessionTotals::default(),
+        })
+    }
+
+    pub fn emit(&mut self, activity: Activity) {
+        match &activity {
+            Activity::Staged { written, .. } => {
+                self.totals.staged += 1;
+                self.totals.files_written += written;
+            }
+            Activity::Removed { .. } => self.totals.removed += 1,
+            Activity::Warning { .. } => self.totals.warnings += 1,
+            Activity::Error { .. } => self.totals.errors += 1,
+            _ => {}
+        }
+
+        let time = Local::now().to_rfc3339();
+        if self.json {
+            let line = JsonLine {
+                time: &time,
+                activity: &activity,
+            };
+            if let Ok(line) = serde_json::to_string(&line) {
+                println!("{}", line);
+            }
+        } else {
+            let message = activity.message();
+            match activity {
+                Activity::Staged { .. } | Activity::Removed { .. } | Activity::Replaced { .. } => {
+                    cli::success(&message)
+                }
+                Activity::Warning { .. } => cli::warn(&message),
+                Activity::Error { .. } => cli::error(&message),
+                _ => cli::info(&message),
+            }
+        }
+
+        if let Some(file) = &mut self.file {
+            // A failing log file must not stop the watcher
+            let _ = writeln!(file, "{} [{}] {}", time, activity.level(), activity.message());
+        }
+    }
+
+    /// Report the end of the session with its totals
+    pub fn finish(&mut self) {
+        let elapsed = self.started.elapsed();
+        let stopped = Activity::Stopped {
+            duration_secs: elapsed.as_secs(),
+            staged: self.totals.staged,
+            files_written: self.totals.files_written,
+            removed: self.totals.removed,
+            warnings: self.totals.warnings,
+            errors: self.totals.errors,
+        };
+        if self.json {
+            self.emit(stopped);
+            return;
+        }
+        if let Some(file) = &mut self.file {
+            let _ = writeln!(
+                file,
+                "{} [{}] {}",
+                Local::now().to_rfc3339(),
+                stopped.level(),
+                stopped.message()
+            );
+        }
+        cli::blank_line();
+        cli::section("Session Summary");
+        cli::kv("Duration", format_duration(elapsed));
+        cli::kv("Staged", self.totals.staged);
+        cli::kv("Files written", self.totals.files_written);
+        cli::kv("Removed", self.totals.removed);
+        cli::kv("Warnings", self.totals.warnings);
+        cli::kv("Errors", self.totals.errors);
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s),
+    }
+}
@@ -1,35 +1,56 @@
 use std::{
+    collections::{HashMap, HashSet},
     fs, io,
     path::{Path, PathBuf},
+    sync::LazyLock,
 };
 
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
+use serde_yaml::Value;
 use walkdir::WalkDir;
 
-use crate::writing::read_markdown_file;
+use crate::embed::Embed;
+use crate::imaging::{plan_images, ProcessedImage};
+use crate::staging::{StageStats, StagingTransaction};
+use crate::writing::{read_markdown_file, RenderedWriting};
 
-use crate::{config::Config, writing::Writing};
+use crate::{
+    config::{AssetDiscovery, Config},
+    writing::Writing,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Asset {
     pub asset_path: String,
 }
 
 pub fn get_asset_list_of_writing(writing: &Writing, config: &Config) -> io::Result<Vec<Asset>> {
-    let (frontmatter, _) = read_markdown_file(&writing.path).unwrap();
-    let prefix = &config.yaml_asset_prefix.as_deref().unwrap_or_default();
-    let writing_prefix = frontmatter[prefix].as_str().unwrap_or("");
-    if writing_prefix.is_empty() {
-        return Ok(Vec::new());
-    }
-    
+    let (frontmatter, body) = read_markdown_file(&writing.path, config)
+        .map_err(|e| io::Error::other(format!("Cannot read {}: {}", writing.path, e)))?;
+
     // Use expanded source asset directory
     let source_asset_dir = config.get_source_asset_dir().unwrap_or_default();
     if source_asset_dir.is_empty() {
         return Ok(Vec::new());
     }
+
+    match config.asset_discovery.unwrap_or_default() {
+        AssetDiscovery::Prefix => Ok(find_assets_by_prefix(&frontmatter, config, &source_asset_dir)),
+        AssetDiscovery::References => find_referenced_assets(&frontmatter, &body, config, &source_asset_dir),
+    }
+}
+
+fn find_assets_by_prefix(frontmatter: &Value, config: &Config, source_asset_dir: &str) -> Vec<Asset> {
+    let prefix = &config.yaml_asset_prefix.as_deref().unwrap_or_default();
+    let writing_prefix = frontmatter[prefix].as_str().unwrap_or("");
+    if writing_prefix.is_empty() {
+        return Vec::new();
+    }
     
     let mut asset_list: Vec<Asset> = Vec::new();
-    for asset in WalkDir::new(&source_asset_dir)
+    for asset in WalkDir::new(source_asset_dir)
         .into_iter()
         .filter_map(|e| e.ok())
     {
@@ -43,30 +64,257 @@ pub fn get_asset_list_of_writing(writing: &Writing, config: &Config) -> io::Resu
             }
         }
     }
-    Ok(asset_list)
+    asset_list
 }
 
-pub fn transfer_asset_files(config: &Config, asset_list: &Vec<Asset>) -> io::Result<()> {
-    if asset_list.is_empty() {
-        return Ok(());
+/// Collect only the assets a writing actually uses.
+///
+/// Images and `![[embeds]]` in the body must exist, otherwise the whole
+/// lookup fails with the list of missing files. Local file links, frontmatter
+/// values naming an asset and the `<assetPrefix>-header` image are picked up
+/// when present.
+fn find_referenced_assets(
+    frontmatter: &Value,
+    body: &str,
+    config: &Config,
+    source_asset_dir: &str,
+) -> io::Result<Vec<Asset>> {
+    let available: HashMap<String, PathBuf> = WalkDir::new(source_asset_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.into_path()))
+        .collect();
+
+    let (required, optional) = collect_body_references(body);
+    let missing: Vec<&String> = required.iter().filter(|name| !available.contains_key(*name)).collect();
+    if !missing.is_empty() {
+        let names: Vec<&str> = missing.iter().map(|name| name.as_str()).collect();
+        return Err(io::Error::other(format!(
+            "{} referenced asset(s) not found in {}: {}",
+            missing.len(),
+            source_asset_dir,
+            names.join(", ")
+        )));
     }
-    
-    // Use expanded target asset directory
-    let target_asset_dir = config.get_target_asset_dir().unwrap_or_default();
-    if target_asset_dir.is_empty() {
-        return Ok(());
+
+    let mut names: Vec<String> = required.into_iter().chain(optional).collect();
+    if let Some(mapping) = frontmatter.as_mapping() {
+        names.extend(
+            mapping
+                .values()
+                .filter_map(|v| v.as_str())
+                .map(|v| file_name_of(v).to_string()),
+        );
     }
-    
-    // Ensure target asset directory exists
-    fs::create_dir_all(&target_asset_dir)?;
-    
-    for asset in asset_list {
-        let path = Path::new(&asset.asset_path);
-        let file_name = path.file_name().unwrap();
-        if path.is_file() {
-            let destination_path = PathBuf::from(&target_asset_dir).join(file_name);
-            fs::copy(path, destination_path)?;
+    let prefix = config.yaml_asset_prefix.as_deref().unwrap_or_default();
+    if let Some(writing_prefix) = frontmatter[prefix].as_str().filter(|p| !p.is_empty()) {
+        let header_stem = format!("{}-header", writing_prefix);
+        names.extend(
+            available
+                .keys()
+                .filter(|name| Path::new(name).file_stem().is_some_and(|s| s.to_string_lossy() == header_stem))
+                .cloned(),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    Ok(names
+        .into_iter()
+        .filter(|name| seen.insert(name.clone()))
+        .filter_map(|name| available.get(&name))
+        .map(|path| Asset {
+            asset_path: path.display().to_string(),
+        })
+        .collect())
+}
+
+/// File names referenced by the body: (images and embeds, local file links).
+/// Embeds inside code spans and code blocks are examples, not references.
+fn collect_body_references(body: &str) -> (Vec<String>, Vec<String>) {
+    let mut required = Vec::new();
+    let mut optional = Vec::new();
+    // The parser splits text at brackets, so runs of text are joined before
+    // looking for `![[...]]`
+    let mut text = String::new();
+    let mut in_code_block = false;
+
+    for event in Parser::new(body) {
+        if !matches!(event, Event::Text(_)) {
+            collect_embeds(&text, &mut required);
+            text.clear();
+        }
+        match event {
+            Event::Text(t) if !in_code_block => text.push_str(&t),
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Start(Tag::Image(_, dest, _)) if is_local(&dest) => {
+                required.push(decoded_file_name(&dest));
+            }
+            Event::Start(Tag::Link(_, dest, _)) if is_local(&dest) => {
+                let name = decoded_file_name(&dest);
+                let is_file = Path::new(&name)
+                    .extension()
+                    .is_some_and(|ext| ext != "md" && ext != "mdx");
+                if is_file {
+                    optional.push(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    collect_embeds(&text, &mut required);
+    (required, optional)
+}
+
+fn collect_embeds(text: &str, files: &mut Vec<String>) {
+    static EMBED_PATTERN: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"!\[\[([^\[\]]+)\]\]").expect("Failed to create embed regex"));
+    for caps in EMBED_PATTERN.captures_iter(text) {
+        let embed = Embed::parse(caps.get(1).map_or("", |m| m.as_str()));
+        if !embed.is_note() {
+            files.push(embed.file_name().to_string());
         }
     }
-    Ok(())
+}
+
+fn is_local(dest: &str) -> bool {
+    !dest.is_empty() && !dest.contains("://") && !dest.starts_with('#') && !dest.starts_with("mailto:")
+}
+
+fn file_name_of(path: &str) -> &str {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn decoded_file_name(dest: &str) -> String {
+    percent_decode_str(file_name_of(dest)).decode_utf8_lossy().to_string()
+}
+
+/// What a transfer would do to the destination of an asset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssetAction {
+    Copy,
+    Overwrite,
+}
+
+#[derive(Debug)]
+pub struct AssetTransfer {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub action: AssetAction,
+    /// Set when the file is an image that gets resized or re-encoded
+    pub processed: Option<ProcessedImage>,
+}
+
+/// Where a writing's assets go: its bundle directory, or the shared `target_asset_dir`
+pub fn target_asset_dir(config: &Config, rendered: &RenderedWriting) -> Option<PathBuf> {
+    match &rendered.bundle_dir {
+        Some(bundle_dir) => Some(bundle_dir.clone()),
+        None => config.get_target_asset_dir().filter(|d| !d.is_empty()).map(PathBuf::from),
+    }
+}
+
+/// Compute the copies `transfer_asset_files` would perform without touching disk.
+pub fn plan_asset_transfer(
+    config: &Config,
+    asset_list: &[Asset],
+    rendered: &RenderedWriting,
+) -> io::Result<Vec<AssetTransfer>> {
+    let Some(target_asset_dir) = target_asset_dir(config, rendered) else {
+        return Ok(Vec::new());
+    };
+
+    let processed_images = plan_images(config, asset_list)?;
+    Ok(asset_list
+        .iter()
+        .map(|asset| PathBuf::from(&asset.asset_path))
+        .filter(|path| path.is_file())
+        .filter_map(|source| {
+            let file_name = source.file_name()?.to_string_lossy().to_string();
+            let processed = processed_images.get(&file_name).cloned();
+            let output_name = processed.as_ref().map_or(file_name, |p| p.file_name.clone());
+            let destination = target_asset_dir.join(output_name);
+            let action = if destination.exists() {
+                AssetAction::Overwrite
+            } else {
+                AssetAction::Copy
+            };
+            Some(AssetTransfer {
+                source,
+                destination,
+                action,
+                processed,
+            })
+        })
+        .collect())
+}
+
+/// Files written for a writing's assets, including unchanged ones
+#[derive(Debug, Default)]
+pub struct TransferredAssets {
+    pub files: Vec<PathBuf>,
+    /// Destinations that already existed before this stage
+    pub overwritten: Vec<PathBuf>,
+    pub stats: StageStats,
+}
+
+/// Queue the assets for the target asset directory, including any responsive
+/// image variants. Destinations that already hold the same content are not rewritten.
+pub fn transfer_asset_files(
+    config: &Config,
+    asset_list: &[Asset],
+    rendered: &RenderedWriting,
+    tx: &mut StagingTransaction,
+) -> io::Result<TransferredAssets> {
+    let transfers = plan_asset_transfer(config, asset_list, rendered)?;
+    let mut result = TransferredAssets::default();
+    result.stats.skipped = asset_list.len().saturating_sub(transfers.len());
+
+    for transfer in transfers {
+        let Some(parent_dir) = transfer.destination.parent() else {
+            continue;
+        };
+        let outcomes = match &transfer.processed {
+            Some(processed) => processed.write(&transfer.source, parent_dir, tx)?,
+            None => {
+                let content = fs::read(&transfer.source)?;
+                let outcome = tx.write(&transfer.destination, &content)?;
+                vec![(transfer.destination, outcome)]
+            }
+        };
+        for (path, outcome) in outcomes {
+            // The transaction is not committed yet, so this is the state before staging
+            if path.exists() {
+                result.overwritten.push(path.clone());
+            }
+            result.stats.record(outcome);
+            result.files.push(path);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_images_embeds_and_file_links() {
+        let body = "![Cat](images/My%20Cat.png) and ![[dog.jpg|A dog|300]]\n\n\
+                    See [the paper](files/paper.pdf), [a post](other.md) and [site](https://example.com/x.png).\n\n\
+                    ![[Other Note]]";
+        let (required, optional) = collect_body_references(body);
+        assert_eq!(required, vec!["My Cat.png", "dog.jpg"]);
+        assert_eq!(optional, vec!["paper.pdf"]);
+    }
+
+    #[test]
+    fn ignores_embeds_in_code() {
+        let body = "Use `![[inline.png]]` to embed.\n\n\
+                    ```markdown\n![[fenced.png]]\n```\n\n    ![[indented.png]]\n\n\
+                    After the code: ![[real.png]]";
+        let (required, _) = collect_body_references(body);
+        assert_eq!(required, vec!["real.png"]);
+    }
 }
@@ -38,6 +38,11 @@ pub fn list_item(text: &str) {
     println!("  - {}", text);
 }
 
+/// A labelled line indented under the previous list item
+pub fn list_detail(label: &str, value: impl Display) {
+    println!("      {} {}", dim(label), value);
+}
+
 pub fn info(message: &str) {
     println!("{} {}", "[INFO]".bright_blue().bold(), message);
 }
@@ -4,6 +4,10 @@ use std::fmt;
 pub enum Command {
     Status,
     Stage(StageOptions),
+    Unstage(UnstageOptions),
+    Publish(PublishOptions),
+    Preview(PreviewOptions),
+    Doctor { project: Option<String> },
     Config,
     Info,
     Project(ProjectCommand),
@@ -13,7 +17,12 @@ pub enum Command {
 #[derive(Debug, Clone)]
 pub enum ProjectCommand {
     List,
-    Create { name: String, description: Option<String> },
+    Create {
+        name: String,
+        description: Option<String>,
+        preset: Option<String>,
+        site: Option<String>,
+    },
     Switch { name: String },
     Delete { name: String },
     Info { name: Option<String> },
@@ -23,6 +32,46 @@ pub enum ProjectCommand {
 #[derive(Debug, Clone)]
 pub struct StageOptions {
     pub continuous: bool,
+    pub all: bool,
+    pub dry_run: bool,
+    pub query: Option<String>,
+    pub filter: StageFilter,
+    /// With `--continuous`, print events as JSON lines
+    pub json: bool,
+    /// With `--continuous`, append timestamped events to this file
+    pub log_file: Option<String>,
+}
+
+/// Filters for batch staging; any filter implies `--all`
+#[derive(Debug, Clone, Default)]
+pub struct StageFilter {
+    pub tag: Option<String>,
+    pub since: Option<String>,
+    pub glob: Option<String>,
+}
+
+impl StageFilter {
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none() && self.since.is_none() && self.glob.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnstageOptions {
+    pub query: String,
+    pub force: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PublishOptions {
+    pub query: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PreviewOptions {
+    /// Preview a single writing instead of every draft
+    pub query: Option<String>,
+    pub port: Option<String>,
 }
 
 impl fmt::Display for Command {
@@ -30,6 +79,10 @@ impl fmt::Display for Command {
         match self {
             Command::Status => write!(f, "status"),
             Command::Stage(_) => write!(f, "stage"),
+            Command::Unstage(_) => write!(f, "unstage"),
+            Command::Publish(_) => write!(f, "publish"),
+            Command::Preview(_) => write!(f, "preview"),
+            Command::Doctor { .. } => write!(f, "doctor"),
             Command::Config => write!(f, "config"),
             Command::Info => write!(f, "info"),
             Command::Project(cmd) => write!(f, "project {}", cmd),
@@ -71,7 +124,43 @@ pub fn parse_command(args: &[String]) -> Option<Command> {
         }
         "stage" => {
             let continuous = args.contains(&"--continuous".to_string());
-            Some(Command::Stage(StageOptions { continuous }))
+            let all = args.contains(&"--all".to_string()) || args.contains(&"--all-drafts".to_string());
+            let dry_run = args.contains(&"--dry-run".to_string());
+            let json = args.contains(&"--json".to_string());
+            let log_file = extract_flag_value(args, "--log-file");
+            let query = extract_positional_args(&args[1..]);
+            let filter = StageFilter {
+                tag: extract_flag_value(args, "--tag"),
+                since: extract_flag_value(args, "--since"),
+                glob: extract_flag_value(args, "--glob"),
+            };
+            Some(Command::Stage(StageOptions {
+                continuous,
+                all,
+                dry_run,
+                query,
+                filter,
+                json,
+                log_file,
+            }))
+        }
+        "unstage" => {
+            let force = args.contains(&"--force".to_string());
+            let query = extract_positional_args(&args[1..])?;
+            Some(Command::Unstage(UnstageOptions { query, force }))
+        }
+        "publish" => {
+            let query = extract_positional_args(&args[1..])?;
+            Some(Command::Publish(PublishOptions { query }))
+        }
+        "preview" => {
+            let query = extract_positional_args(&args[1..]);
+            let port = extract_flag_value(args, "--port");
+            Some(Command::Preview(PreviewOptions { query, port }))
+        }
+        "doctor" => {
+            let project = extract_positional_args(&args[1..]);
+            Some(Command::Doctor { project })
         }
         "config" => Some(Command::Config),
         "info" => Some(Command::Info),
@@ -93,12 +182,15 @@ fn parse_project_command(args: &[String]) -> Option<Command> {
                 return None;
             }
             let name = args[1].clone();
-            let description = if args.len() > 2 {
-                Some(args[2..].join(" "))
-            } else {
-                None
-            };
-            Some(Command::Project(ProjectCommand::Create { name, description }))
+            let description = extract_positional_args(&args[2..]);
+            let preset = extract_flag_value(args, "--preset");
+            let site = extract_flag_value(args, "--site");
+            Some(Command::Project(ProjectCommand::Create {
+                name,
+                description,
+                preset,
+                site,
+            }))
         }
         "switch" => {
             if args.len() < 2 {
@@ -134,11 +226,36 @@ fn parse_project_command(args: &[String]) -> Option<Command> {
     }
 }
 
+/// Flags that take a value, so the value is not mistaken for a positional argument
+const VALUE_FLAGS: &[&str] = &["--project", "--tag", "--since", "--glob", "--preset", "--site", "--log-file", "--port"];
+
 fn extract_project_flag(args: &[String]) -> Option<String> {
+    extract_flag_value(args, "--project")
+}
+
+fn extract_flag_value(args: &[String], flag: &str) -> Option<String> {
     for i in 0..args.len() {
-        if args[i] == "--project" && i + 1 < args.len() {
+        if args[i] == flag && i + 1 < args.len() {
             return Some(args[i + 1].clone());
         }
     }
     None
 }
+
+/// Collect the non-flag arguments of a command, skipping flag values such as `--project <name>`.
+fn extract_positional_args(args: &[String]) -> Option<String> {
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with("--") {
+            positional.push(arg.as_str());
+        }
+    }
+    if positional.is_empty() {
+        None
+    } else {
+        Some(positional.join(" "))
+    }
+}
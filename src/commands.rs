@@ -1,18 +1,32 @@
+use std::collections::HashSet;
 use std::env;
-use std::path::Path;
-use std::sync::mpsc::channel;
-use std::time::Duration;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::NaiveDate;
+use colored::Colorize;
+use globset::Glob;
+use serde_yaml::Value;
+use similar::TextDiff;
 
-use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
-
-use crate::asset::{get_asset_list_of_writing, transfer_asset_files};
+use crate::activity::{Activity, ActivityLog};
+use crate::asset::{get_asset_list_of_writing, plan_asset_transfer, AssetAction};
 use crate::cli;
-use crate::command::{ProjectCommand, StageOptions};
-use crate::config::Config;
-use crate::project::get_project_manager;
+use crate::command::{
+    PreviewOptions, ProjectCommand, PublishOptions, StageFilter, StageOptions, UnstageOptions,
+};
+use crate::config::{expand_tilde, AssetDiscovery, Config};
+use crate::doctor;
+use crate::document::{Document, FrontmatterFormat};
+use crate::links::LinkResolver;
+use crate::preset::{Preset, PRESET_NAMES};
+use crate::preview;
+use crate::project::{get_project_manager, ProjectConfig};
+use crate::staging::{stage_files, StageOutput, StageStats, StagedEntry, StagingLedger};
+use crate::watch::{self, SourceChange, SourceWatcher, WritingIndex};
+use crate::transform::{build_pipeline, BUILTIN_TRANSFORMS};
 use crate::writing::{
-    create_writing_list, print_writing_list, select_draft_writing_from_list,
-    update_writing_content_and_transfer,
+    create_writing_list, find_writing, print_writing_list, read_markdown_file, render_writing,
+    scan_writings, select_draft_writing_from_list, ParseDiagnostic, Writing,
 };
 
 // ── Info ────────────────────────────────────────────────────────────────────
@@ -26,16 +40,40 @@ pub fn execute_info_command() {
     cli::section("Commands");
     cli::list_item("status      Show drafts and published writings");
     cli::list_item("stage       Stage drafts and transfer content");
+    cli::list_item("unstage     Remove a staged writing and its copied assets");
+    cli::list_item("publish     Mark a draft as published in the source and stage it");
+    cli::list_item("preview     Serve the staged drafts as HTML with live reload");
+    cli::list_item("doctor      Check projects for configuration and note problems");
     cli::list_item("config      Edit or inspect configuration");
     cli::list_item("dashboard   Launch the interactive TUI");
     cli::blank_line();
     cli::section("Stage Options");
+    cli::list_item("<writing>      Stage by path, title or slug without prompting");
+    cli::list_item("--all          Stage every draft without prompting (alias: --all-drafts)");
+    cli::list_item("--tag <tag>    With --all, only drafts carrying this tag");
+    cli::list_item("--since <date> With --all, only drafts dated on or after YYYY-MM-DD");
+    cli::list_item("--glob <pat>   With --all, only drafts whose path matches the pattern");
+    cli::list_item("--dry-run      Show the changes staging would make, write nothing");
     cli::list_item("--continuous   Watch source folder and stage on changes");
+    cli::list_item("--json         With --continuous, print events as JSON lines");
+    cli::list_item("--log-file <path>  With --continuous, append timestamped events to a file");
     cli::list_item("--project <name>  Use a specific project");
     cli::blank_line();
+    cli::section("Unstage Options");
+    cli::list_item("<writing>      Writing to unstage, by path, title or slug");
+    cli::list_item("--force        Delete target files even if modified after staging");
+    cli::blank_line();
+    cli::section("Preview Options");
+    cli::list_item("<writing>      Preview one writing instead of every draft");
+    cli::list_item(&format!("--port <port>  Port to serve on (default {})", preview::DEFAULT_PORT));
+    cli::blank_line();
     cli::section("Project Management");
     cli::list_item("project list           List projects and show active");
     cli::list_item("project create <name>  Create a project");
+    cli::list_item(&format!(
+        "  --preset <name> [--site <dir>]  Start from a generator preset ({})",
+        PRESET_NAMES.join(", ")
+    ));
     cli::list_item("project switch <name>  Switch active project");
     cli::list_item("project delete <name>  Delete a project (not active)");
     cli::list_item("project info [name]    Show project details");
@@ -46,7 +84,10 @@ pub fn execute_info_command() {
     cli::list_item("lazydraft project create my-blog \"Personal blog content\"");
     cli::list_item("lazydraft project switch my-blog");
     cli::list_item("lazydraft status");
+    cli::list_item("lazydraft doctor my-blog");
+    cli::list_item("lazydraft stage my-first-post");
     cli::list_item("lazydraft stage --continuous");
+    cli::list_item("lazydraft preview");
     cli::blank_line();
     cli::section("Documentation");
     cli::list_item("https://github.com/yigitozgumus/lazydraft");
@@ -54,105 +95,646 @@ pub fn execute_info_command() {
 
 // ── Status ──────────────────────────────────────────────────────────────────
 
-pub fn execute_status_command(config: &Config) -> std::io::Result<()> {
-    match create_writing_list(config) {
-        Ok(writings) => print_writing_list(writings),
+pub fn execute_status_command(project: &ProjectConfig) -> std::io::Result<()> {
+    let scan = match scan_writings(&project.config) {
+        Ok(scan) => scan,
         Err(_) => exit_with_message("Couldn't print the writing list!"),
+    };
+    let writings = scan.writings;
+    let ledger = get_project_manager()
+        .and_then(|pm| pm.load_staging_ledger(&project.name))
+        .unwrap_or_else(|e| {
+            cli::warn(&format!("Could not read staging ledger: {}", e));
+            StagingLedger::default()
+        });
+
+    print_writing_list(writings.clone());
+    if !ledger.entries.is_empty() {
+        cli::blank_line();
+        print_staged_writings(&ledger, &writings);
+    }
+    if !scan.diagnostics.is_empty() {
+        cli::blank_line();
+        print_diagnostics(&scan.diagnostics);
     }
     Ok(())
 }
 
+fn print_diagnostics(diagnostics: &[ParseDiagnostic]) {
+    cli::section("Problems");
+    for diagnostic in diagnostics {
+        let label = if diagnostic.skipped {
+            "skipped".red().bold()
+        } else {
+            "warning".yellow().bold()
+        };
+        cli::list_item(&format!("{} {}", label, diagnostic));
+    }
+    let skipped = diagnostics.iter().filter(|d| d.skipped).count();
+    if skipped > 0 {
+        cli::info(&format!(
+            "{} note(s) are not listed until their frontmatter is fixed. Run `lazydraft doctor` for more checks.",
+            skipped
+        ));
+    }
+}
+
+fn print_staged_writings(ledger: &StagingLedger, writings: &[Writing]) {
+    cli::section("Staged");
+    for entry in &ledger.entries {
+        let title = writings
+            .iter()
+            .find(|w| w.path == entry.source_path)
+            .map(|w| w.title.clone())
+            .unwrap_or_else(|| format!("{} (source missing)", entry.source_path));
+        cli::list_item(&title);
+        cli::kv("Target", &entry.target_path);
+        cli::kv("Staged at", format_timestamp(&entry.staged_at));
+    }
+}
+
 // ── Stage ───────────────────────────────────────────────────────────────────
 
-pub fn execute_stage_command(config: &Config, options: StageOptions) -> std::io::Result<()> {
+pub fn execute_stage_command(project: &ProjectConfig, options: StageOptions) -> std::io::Result<()> {
+    if !options.continuous && (options.json || options.log_file.is_some()) {
+        return Err(std::io::Error::other("--json and --log-file only apply to --continuous"));
+    }
     if options.continuous {
-        execute_continuous_stage(config)
+        if options.dry_run {
+            return Err(std::io::Error::other("--dry-run cannot be combined with --continuous"));
+        }
+        execute_continuous_stage(project, options.json, options.log_file.as_deref())
+    } else if options.all || !options.filter.is_empty() {
+        if options.query.is_some() {
+            return Err(std::io::Error::other("A writing name cannot be combined with --all or filters"));
+        }
+        execute_batch_stage(project, &options.filter, options.dry_run)
     } else {
-        execute_single_stage(config)
+        execute_single_stage(project, options.query.as_deref(), options.dry_run)
     }
 }
 
-fn execute_single_stage(config: &Config) -> std::io::Result<()> {
-    let writing_list = create_writing_list(config)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
-    let selected_writing =
-        select_draft_writing_from_list(&writing_list)
-            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "No draft writing selected"))?;
-    let asset_list = get_asset_list_of_writing(selected_writing, config)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{}", e)))?;
+fn execute_single_stage(project: &ProjectConfig, query: Option<&str>, dry_run: bool) -> std::io::Result<()> {
+    let writing_list = create_writing_list(&project.config)
+        .map_err(|e| std::io::Error::other(format!("{}", e)))?;
+    let selected_writing = match query {
+        Some(query) => find_writing(&writing_list, query).map_err(std::io::Error::other)?,
+        None => select_draft_writing_from_list(&writing_list)
+            .ok_or_else(|| std::io::Error::other("No draft writing selected"))?,
+    };
 
-    transfer_asset_files(config, &asset_list)?;
-    update_writing_content_and_transfer(config, selected_writing, &asset_list)?;
-    cli::success("Writing transferred successfully.");
+    let links = LinkResolver::new(&writing_list);
+    if dry_run {
+        return preview_stage(&project.config, selected_writing, &links);
+    }
+    let staged = stage_writing(project, selected_writing, &links)?;
+    staged.report();
+    cli::success(&format!("Writing transferred successfully ({}).", staged.stats));
     Ok(())
 }
 
-fn execute_continuous_stage(config: &Config) -> std::io::Result<()> {
-    cli::info("Starting continuous staging mode...");
+fn execute_batch_stage(project: &ProjectConfig, filter: &StageFilter, dry_run: bool) -> std::io::Result<()> {
+    let writing_list = create_writing_list(&project.config)
+        .map_err(|e| std::io::Error::other(format!("{}", e)))?;
+    let selected = select_batch_writings(&project.config, &writing_list, filter).map_err(std::io::Error::other)?;
+    if selected.is_empty() {
+        cli::warn("No draft writings match the given filters");
+        return Ok(());
+    }
 
-    let (tx, rx) = channel();
+    let links = LinkResolver::new(&writing_list);
+    if dry_run {
+        for writing in &selected {
+            preview_stage(&project.config, writing, &links)?;
+            cli::blank_line();
+        }
+        return Ok(());
+    }
 
-    let mut watcher = RecommendedWatcher::new(
-        tx,
-        NotifyConfig::default().with_poll_interval(Duration::from_secs(2)),
-    )
-    .expect("Failed to create file watcher");
+    let mut results = Vec::new();
+    let mut totals = StageStats::default();
+    for writing in &selected {
+        let result = stage_writing(project, writing, &links).map(|staged| {
+            staged.report();
+            staged.stats
+        });
+        if let Ok(stats) = &result {
+            totals.merge(*stats);
+        }
+        results.push((*writing, result));
+    }
 
-    watcher
-        .watch(
-            Path::new(&config.get_source_dir().unwrap_or_default()),
-            RecursiveMode::Recursive,
-        )
-        .expect("Failed to start watching directory");
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    cli::blank_line();
+    cli::section("Summary");
+    for (writing, result) in &results {
+        match result {
+            Ok(stats) => cli::kv(&writing.title, format!("{} ({})", "staged".green(), stats)),
+            Err(e) => cli::kv(&writing.title, format!("{} {}", "failed:".red(), e)),
+        }
+    }
+    cli::blank_line();
+    cli::kv("Writings", format!("{} staged, {} failed", results.len() - failed, failed));
+    cli::kv("Files", totals);
+    cli::blank_line();
 
-    cli::info(&format!(
-        "Watching for changes in: {}",
-        config.get_source_dir().unwrap_or_default()
-    ));
+    if failed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{} of {} draft(s) failed to stage",
+            failed,
+            results.len()
+        )));
+    }
+    cli::success(&format!("Staged {} draft(s).", results.len()));
+    Ok(())
+}
 
-    let conf = config.clone();
-
-    loop {
-        match rx.recv() {
-            Ok(event) => match event {
-                Ok(event) => {
-                    if event.kind.is_modify() {
-                        cli::info("Change detected, running stage process...");
-
-                        match create_writing_list(&conf) {
-                            Ok(writing_list) => {
-                                if let Some(modified_writing) = writing_list.iter().find(|w| {
-                                    event.paths.iter().any(|p| p.to_string_lossy().contains(&w.path))
-                                }) {
-                                    if modified_writing.is_draft {
-                                        match get_asset_list_of_writing(modified_writing, &conf) {
-                                            Ok(asset_list) => {
-                                                match transfer_asset_files(&conf, &asset_list) {
-                                                    Ok(_) => {
-                                                        match update_writing_content_and_transfer(&conf, modified_writing, &asset_list) {
-                                                            Ok(_) => cli::success(&format!("Staged changes for: {}", modified_writing.title)),
-                                                            Err(e) => cli::error(&format!("Error updating content: {}", e)),
-                                                        }
-                                                    }
-                                                    Err(e) => cli::error(&format!("Error transferring assets: {}", e)),
-                                                }
-                                            }
-                                            Err(e) => cli::error(&format!("Error getting asset list: {}", e)),
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => cli::error(&format!("Error creating writing list: {}", e)),
-                        }
-                    }
+/// Drafts matching every given filter, in writing list order
+fn select_batch_writings<'a>(
+    config: &Config,
+    writings: &'a [Writing],
+    filter: &StageFilter,
+) -> Result<Vec<&'a Writing>, String> {
+    let since = filter
+        .since
+        .as_deref()
+        .map(|date| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Invalid --since date '{}', expected YYYY-MM-DD", date))
+        })
+        .transpose()?;
+    let glob = filter
+        .glob
+        .as_deref()
+        .map(|pattern| {
+            Glob::new(pattern)
+                .map(|g| g.compile_matcher())
+                .map_err(|e| format!("Invalid --glob pattern '{}': {}", pattern, e))
+        })
+        .transpose()?;
+    let source_dir = config.get_source_dir().unwrap_or_default();
+
+    Ok(writings
+        .iter()
+        .filter(|w| w.is_draft)
+        .filter(|w| since.is_none_or(|since| w.publish_date.is_some_and(|date| date >= since)))
+        .filter(|w| {
+            glob.as_ref().is_none_or(|glob| {
+                let relative = Path::new(&w.path).strip_prefix(&source_dir).unwrap_or(Path::new(&w.path));
+                glob.is_match(relative)
+            })
+        })
+        .filter(|w| filter.tag.as_deref().is_none_or(|tag| writing_has_tag(config, w, tag)))
+        .collect())
+}
+
+fn writing_has_tag(config: &Config, writing: &Writing, tag: &str) -> bool {
+    let Ok((frontmatter, _)) = read_markdown_file(&writing.path, config) else {
+        return false;
+    };
+    match &frontmatter["tags"] {
+        Value::Sequence(tags) => tags
+            .iter()
+            .filter_map(|t| t.as_str())
+            .any(|t| t.eq_ignore_ascii_case(tag)),
+        Value::String(t) => t.split(',').any(|t| t.trim().eq_ignore_ascii_case(tag)),
+        _ => false,
+    }
+}
+
+/// What staging one writing did, with the warnings left for the caller to report
+struct StagedWriting {
+    stats: StageStats,
+    warnings: Vec<String>,
+    /// Output of an earlier stage to a different target, deleted
+    removed: Vec<String>,
+}
+
+impl StagedWriting {
+    fn report(&self) {
+        for warning in &self.warnings {
+            cli::warn(warning);
+        }
+        for path in &self.removed {
+            cli::info(&format!("Removed previous output {}", path));
+        }
+    }
+}
+
+/// Run the asset transfer and content pipeline for a single writing and
+/// record it in the project's staging ledger.
+fn stage_writing(project: &ProjectConfig, writing: &Writing, links: &LinkResolver) -> std::io::Result<StagedWriting> {
+    let config = &project.config;
+    let asset_list = get_asset_list_of_writing(writing, config)?;
+    let StageOutput {
+        rendered,
+        asset_files,
+        overwritten_assets,
+        stats,
+    } = stage_files(config, writing, &asset_list, links)?;
+    let mut warnings: Vec<String> = rendered
+        .warnings
+        .iter()
+        .map(|warning| format!("{}: {}", writing.title, warning))
+        .collect();
+
+    let recorded = get_project_manager().and_then(|pm| {
+        let mut ledger = pm.load_staging_ledger(&project.name)?;
+        let stale = ledger.record(StagedEntry::new(&writing.path, &rendered, &asset_files, &overwritten_assets));
+        pm.save_staging_ledger(&project.name, &ledger)?;
+        Ok(stale)
+    });
+    let removed = match recorded {
+        Ok(stale) => {
+            warnings.extend(stale.warnings().map(|warning| format!("{}: {}", writing.title, warning)));
+            stale.removed
+        }
+        Err(e) => {
+            warnings.push(format!(
+                "{}: staged, but could not update the staging ledger: {}",
+                writing.title, e
+            ));
+            Vec::new()
+        }
+    };
+    Ok(StagedWriting {
+        stats,
+        warnings,
+        removed,
+    })
+}
+
+/// Run the staging pipeline in memory and print what it would change on disk.
+fn preview_stage(config: &Config, writing: &Writing, links: &LinkResolver) -> std::io::Result<()> {
+    let asset_list = get_asset_list_of_writing(writing, config)?;
+    let rendered = render_writing(config, writing, &asset_list, links)?;
+    let target_label = rendered.target_path.display().to_string();
+    for warning in &rendered.warnings {
+        cli::warn(warning);
+    }
+
+    cli::section(&format!("Dry run: {}", writing.title));
+    cli::kv("Source", &writing.path);
+    cli::kv("Target", &target_label);
+    cli::blank_line();
+
+    match std::fs::read_to_string(&rendered.target_path) {
+        Ok(existing) if existing == rendered.content => cli::info("Target file is unchanged."),
+        Ok(existing) => print_unified_diff(&existing, &rendered.content, &target_label),
+        Err(_) => {
+            cli::info("Target file does not exist yet and would be created.");
+            print_unified_diff("", &rendered.content, &target_label);
+        }
+    }
+
+    let transfers = plan_asset_transfer(config, &asset_list, &rendered)?;
+    cli::blank_line();
+    cli::section("Assets");
+    if transfers.is_empty() {
+        cli::list_item("No assets to transfer");
+    }
+    for transfer in &transfers {
+        let action = match transfer.action {
+            AssetAction::Copy => "copy".green(),
+            AssetAction::Overwrite => "overwrite".yellow(),
+        };
+        let variants = match &transfer.processed {
+            Some(processed) if !processed.variants.is_empty() => {
+                format!(" (+{} responsive variants)", processed.variants.len())
+            }
+            _ => String::new(),
+        };
+        cli::list_item(&format!(
+            "{:<9} {} -> {}{}",
+            action,
+            transfer.source.display(),
+            transfer.destination.display(),
+            variants
+        ));
+    }
+    cli::blank_line();
+    cli::info("Dry run, nothing was written.");
+    Ok(())
+}
+
+fn print_unified_diff(old: &str, new: &str, label: &str) {
+    let diff = TextDiff::from_lines(old, new);
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("{} (current)", label), &format!("{} (staged)", label))
+        .to_string();
+    for line in unified.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!("{}", line.bold());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+fn execute_continuous_stage(project: &ProjectConfig, json: bool, log_file: Option<&str>) -> std::io::Result<()> {
+    let config = &project.config;
+    let mut watcher = SourceWatcher::new(config).map_err(std::io::Error::other)?;
+    let mut index = WritingIndex::build(config).map_err(|e| std::io::Error::other(e.to_string()))?;
+    let mut log = ActivityLog::new(json, log_file).map_err(std::io::Error::other)?;
+
+    // Signals only raise the flag, so a stage in progress always completes
+    let stop = watch::shutdown_flag()?;
+    log.emit(Activity::Started {
+        project: project.name.clone(),
+        roots: watcher.roots().iter().map(|root| root.display().to_string()).collect(),
+    });
+
+    report_missing_sources(project, &index, &mut log).map_err(std::io::Error::other)?;
+
+    let result = watcher.run(&stop, |batch| match batch {
+        Ok(changes) => apply_source_changes(project, &mut index, changes, &mut log),
+        Err(e) => log.emit(Activity::error(format!("Watch error: {}", e))),
+    });
+
+    // Saves still inside the quiet period are not lost on exit
+    let pending = watcher.drain();
+    if !pending.is_empty() {
+        log.emit(Activity::Stopping { pending: pending.len() });
+        apply_source_changes(project, &mut index, pending, &mut log);
+    }
+    log.finish();
+    result.map_err(std::io::Error::other)
+}
+
+/// Point out staged notes whose source disappeared while nothing was watching.
+///
+/// A missing file alone is not proof of a deletion: the vault may be on an
+/// unmounted drive or half-synced. Staged files are only removed for delete
+/// events seen while watching, or by an explicit `lazydraft unstage`.
+fn report_missing_sources(project: &ProjectConfig, index: &WritingIndex, log: &mut ActivityLog) -> Result<(), String> {
+    let ledger = get_project_manager()?.load_staging_ledger(&project.name)?;
+    let source_dir = project.config.get_source_dir().unwrap_or_default();
+    if !Path::new(&source_dir).is_dir() || index.writings().next().is_none() {
+        if !ledger.entries.is_empty() {
+            log.emit(Activity::warning(format!(
+                "{} is missing or has no writings, not checking staged notes against it",
+                source_dir
+            )));
+        }
+        return Ok(());
+    }
+    for entry in ledger.entries.iter().filter(|e| !Path::new(&e.source_path).exists()) {
+        log.emit(Activity::warning(format!(
+            "{} was removed while not watching, its staged files are kept. Remove them with: lazydraft unstage '{}'",
+            file_label(Path::new(&entry.source_path)),
+            entry.source_path
+        )));
+    }
+    Ok(())
+}
+
+/// Bring `target_dir` in line with a batch of settled changes in the vault,
+/// staging each writing at most once
+fn apply_source_changes(
+    project: &ProjectConfig,
+    index: &mut WritingIndex,
+    changes: Vec<SourceChange>,
+    log: &mut ActivityLog,
+) {
+    let config = &project.config;
+    // Read once per batch; stages only record entries after the changes are sorted
+    let staged: HashSet<String> = get_project_manager()
+        .and_then(|pm| pm.load_staging_ledger(&project.name))
+        .map(|ledger| ledger.entries.into_iter().map(|e| e.source_path).collect())
+        .unwrap_or_else(|e| {
+            log.emit(Activity::warning(format!("Could not read staging ledger: {}", e)));
+            HashSet::new()
+        });
+    let is_staged = |writing: &Writing| staged.contains(&writing.path);
+    let mut restage: Vec<(Writing, String)> = Vec::new();
+    let mut queue = |writing: Writing, reason: String| {
+        if !restage.iter().any(|(w, _)| w.path == writing.path) {
+            restage.push((writing, reason));
+        }
+    };
+
+    for change in changes {
+        match change {
+            SourceChange::Changed(path) => match index.refresh(config, &path) {
+                Ok(writing) if writing.is_draft || is_staged(&writing) => {
+                    queue(writing, "edited".to_string())
                 }
-                Err(e) => cli::error(&format!("Watch error: {}", e)),
+                Ok(_) => {}
+                Err(diagnostic) => log.emit(Activity::warning(diagnostic.to_string())),
             },
-            Err(e) => cli::error(&format!("Watch error: {}", e)),
+            SourceChange::Renamed { from, to } => {
+                index.remove(&from);
+                let was_staged = match unstage_missing_note(project, &from) {
+                    Ok(removed) => removed.is_some(),
+                    Err(e) => {
+                        log.emit(Activity::warning(e));
+                        true
+                    }
+                };
+                match index.refresh(config, &to) {
+                    Ok(writing) if writing.is_draft || was_staged => {
+                        queue(writing, format!("renamed from {}", file_label(&from)))
+                    }
+                    Ok(_) => {}
+                    Err(diagnostic) => log.emit(Activity::warning(diagnostic.to_string())),
+                }
+            }
+            SourceChange::Removed(path) => {
+                index.remove(&path);
+                report_unstage(project, &path, log);
+            }
+            SourceChange::AssetChanged(path) => {
+                for writing in index.users_of(config, &path, |w| w.is_draft || is_staged(w)) {
+                    queue(writing, format!("{} changed", file_label(&path)));
+                }
+            }
+        }
+    }
+
+    let links = LinkResolver::new(index.writings());
+    for (writing, reason) in restage {
+        match stage_writing(project, &writing, &links) {
+            Ok(staged) => {
+                for warning in staged.warnings {
+                    log.emit(Activity::warning(warning));
+                }
+                if !staged.removed.is_empty() {
+                    log.emit(Activity::Replaced {
+                        title: writing.title.clone(),
+                        files: staged.removed.len(),
+                    });
+                }
+                // Saves that produce identical output are not worth a log line
+                if staged.stats.written > 0 {
+                    log.emit(Activity::staged(&writing.title, &writing.path, &reason, staged.stats));
+                }
+            }
+            Err(e) => log.emit(Activity::error(format!("Error staging {}: {}", writing.title, e))),
         }
     }
 }
 
+fn file_label(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Delete the staged output of a note that was removed or renamed in the
+/// vault. Returns the number of deleted files, or `None` if it wasn't staged.
+fn unstage_missing_note(project: &ProjectConfig, path: &Path) -> Result<Option<usize>, String> {
+    let source_path = path.display().to_string();
+    let project_manager = get_project_manager()?;
+    let mut ledger = project_manager.load_staging_ledger(&project.name)?;
+    if !ledger.contains(&source_path) {
+        return Ok(None);
+    }
+    let removed = ledger
+        .unstage(&source_path, false)
+        .map_err(|e| format!("Kept the staged files of {}: {}", source_path, e))?;
+    project_manager.save_staging_ledger(&project.name, &ledger)?;
+    Ok(Some(removed.len()))
+}
+
+fn report_unstage(project: &ProjectConfig, path: &Path, log: &mut ActivityLog) {
+    match unstage_missing_note(project, path) {
+        Ok(Some(files)) => log.emit(Activity::Removed {
+            source: file_label(path),
+            files,
+        }),
+        Ok(None) => {}
+        Err(e) => log.emit(Activity::warning(e)),
+    }
+}
+
+// ── Unstage ─────────────────────────────────────────────────────────────────
+
+pub fn execute_unstage_command(project: &ProjectConfig, options: UnstageOptions) -> Result<(), String> {
+    let project_manager = get_project_manager()?;
+    let mut ledger = project_manager.load_staging_ledger(&project.name)?;
+    let writing_list = create_writing_list(&project.config).map_err(|e| e.to_string())?;
+
+    // Fall back to the raw source path so notes deleted from the vault can still be unstaged
+    let source_path = match find_writing(&writing_list, &options.query) {
+        Ok(writing) => writing.path.clone(),
+        Err(_) if ledger.contains(&options.query) => options.query.clone(),
+        Err(e) => return Err(e),
+    };
+
+    let modified = ledger.modified_files(&source_path);
+    if !modified.is_empty() && !options.force {
+        for path in &modified {
+            cli::warn(&format!("Modified after staging: {}", path));
+        }
+        return Err("Refusing to delete modified files. Use --force to delete anyway.".to_string());
+    }
+
+    let removed = ledger.unstage(&source_path, options.force)?;
+    project_manager.save_staging_ledger(&project.name, &ledger)?;
+
+    for path in &removed {
+        cli::list_item(&format!("removed {}", path));
+    }
+    cli::success(&format!("Unstaged {} ({} files removed)", source_path, removed.len()));
+    Ok(())
+}
+
+// ── Publish ─────────────────────────────────────────────────────────────────
+
+/// Mark a draft as published in the source vault, then stage it.
+///
+/// The source note is edited in place so its formatting survives. If staging
+/// fails, the original note is restored.
+pub fn execute_publish_command(project: &ProjectConfig, options: PublishOptions) -> Result<(), String> {
+    let config = &project.config;
+    let writing_list = create_writing_list(config).map_err(|e| e.to_string())?;
+    let writing = find_writing(&writing_list, &options.query)?;
+    if !writing.is_draft {
+        return Err(format!("'{}' is already published. Use `lazydraft stage` to restage it.", writing.title));
+    }
+
+    let original = fs::read_to_string(&writing.path).map_err(|e| format!("Cannot read {}: {}", writing.path, e))?;
+    let document = Document::parse(&original)?;
+    let mut frontmatter = document.frontmatter.clone();
+    if !frontmatter.is_mapping() {
+        frontmatter = Value::Mapping(Default::default());
+    }
+
+    // Edit whichever spelling the note uses: the mapped alias or LazyDraft's own name
+    let key_map = config.frontmatter_map();
+    let source_key = |key: &str| {
+        let alias = key_map.source.get(key).cloned().unwrap_or_else(|| key.to_string());
+        if frontmatter[alias.as_str()].is_null() && !frontmatter[key].is_null() {
+            key.to_string()
+        } else {
+            alias
+        }
+    };
+    let draft_key = source_key("draft");
+    let date_key = source_key("publishDate");
+
+    // A string status such as `status: draft` gets a string value back
+    let draft_value = if frontmatter[draft_key.as_str()].is_string() {
+        Value::String("published".to_string())
+    } else {
+        Value::Bool(false)
+    };
+    let existing_date = frontmatter[date_key.as_str()]
+        .as_str()
+        .filter(|d| !d.trim().is_empty())
+        .map(String::from);
+    let publish_date = existing_date.unwrap_or_else(|| {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        frontmatter[date_key.as_str()] = Value::String(today.clone());
+        today
+    });
+    frontmatter[draft_key.as_str()] = draft_value;
+    let updated = document.render(&frontmatter, &document.body, &Default::default());
+
+    fs::write(&writing.path, &updated).map_err(|e| format!("Cannot write {}: {}", writing.path, e))?;
+    let staged = create_writing_list(config).map_err(|e| e.to_string()).and_then(|writings| {
+        let published = writings
+            .iter()
+            .find(|w| w.path == writing.path)
+            .ok_or_else(|| format!("{} disappeared from the writing list", writing.path))?;
+        stage_writing(project, published, &LinkResolver::new(&writings)).map_err(|e| e.to_string())
+    });
+    let staged = match staged {
+        Ok(staged) => staged,
+        Err(e) => {
+            fs::write(&writing.path, &original).map_err(|re| format!("{} (and restoring the source failed: {})", e, re))?;
+            return Err(format!("Staging failed, source note left unchanged: {}", e));
+        }
+    };
+
+    staged.report();
+    cli::kv("Source", &writing.path);
+    cli::kv("Publish date", &publish_date);
+    cli::kv("Files", staged.stats);
+    cli::success(&format!("Published: {}", writing.title));
+    Ok(())
+}
+
+// ── Preview ─────────────────────────────────────────────────────────────────
+
+pub fn execute_preview_command(project: &ProjectConfig, options: PreviewOptions) -> Result<(), String> {
+    let port = match options.port {
+        Some(port) => port
+            .parse::<u16>()
+            .map_err(|_| format!("Invalid --port '{}', expected a number up to 65535", port))?,
+        None => preview::DEFAULT_PORT,
+    };
+    preview::serve(&project.config, options.query, port)
+}
+
 // ── Config ──────────────────────────────────────────────────────────────────
 
 pub fn execute_config_command(args: Vec<String>) {
@@ -237,19 +819,63 @@ fn display_config_info() {
     cli::kv("target_asset_prefix", "Prefix for asset links in the generated files.");
     cli::kv("target_hero_image_prefix", "Prefix for hero image links in the output.");
     cli::kv("yaml_asset_prefix", "Prefix for assets referenced in YAML frontmatter.");
+    cli::kv("asset_discovery", "'prefix' (default) matches asset names against the prefix, 'references' copies only embedded files.");
+    cli::kv("layout", "'flat' (default) writes target_dir/<name>.md, 'bundle' writes target_dir/<slug>/index.md with its assets.");
     cli::kv("sanitize_frontmatter", "If true, removes empty fields from the frontmatter.");
     cli::kv("auto_add_cover_img", "Automatically adds a cover image to the frontmatter.");
     cli::kv("auto_add_hero_img", "Automatically adds a hero image to the frontmatter.");
     cli::kv("remove_draft_on_stage", "Sets the 'draft' flag to false when staging.");
     cli::kv("add_date_prefix", "Adds a date prefix to the file name.");
+    cli::kv("debounce_ms", "Quiet period before watch mode stages a changed file (default 1000).");
+    cli::kv("filename_template", "Output file name such as '{date}-{slug}.{ext}', replaces add_date_prefix.");
     cli::kv("remove_wikilinks", "Converts wiki-style links to plain markdown links.");
     cli::kv("trim_tags", "Strips a specified prefix from tags in frontmatter.");
     cli::kv("tag_prefix", "The prefix to strip from tags when 'trim_tags' is enabled.");
     cli::kv("use_mdx_format", "If true, saves output files with the .mdx extension instead of .md.");
+    cli::kv("pipeline", "Ordered list of transforms to run. Replaces the boolean toggles above.");
+    cli::kv("[discovery]", "extensions, include and exclude globs selecting notes in source_dir; also reads .lazydraftignore.");
+    cli::kv("[frontmatter]", "format ('yaml', 'toml' or 'json') for staged files, plus map.source / map.target key names.");
+    cli::kv("[images]", "max_width, format ('original' or 'webp'), strip_metadata and srcset_widths for copied images.");
+    cli::kv("preset", "Generator preset the project was created with; enables its checks on staged files.");
+    cli::blank_line();
+    cli::section("Pipeline Transforms");
+    for (name, description) in BUILTIN_TRANSFORMS {
+        cli::kv(name, description);
+    }
     cli::blank_line();
     cli::info("Use `lazydraft config --edit` to modify these settings.");
 }
 
+// ── Doctor ──────────────────────────────────────────────────────────────────
+
+/// Check every project, or only `name`, and print a report with fixes
+pub fn execute_doctor_command(name: Option<String>) -> Result<(), String> {
+    let project_manager = get_project_manager()?;
+    let projects = match name {
+        Some(name) => vec![project_manager.load_project(&name)?],
+        None => project_manager.list_projects()?,
+    };
+    if projects.is_empty() {
+        cli::warn("No projects found. Create one with 'lazydraft project create <name>'");
+        return Ok(());
+    }
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    for project in &projects {
+        let findings = doctor::check_project(&project.config);
+        let project_errors = doctor::print_report(&project.name, &findings);
+        errors += project_errors;
+        warnings += findings.len() - project_errors;
+    }
+    match errors {
+        0 if warnings == 0 => cli::success(&format!("{} project(s) look healthy", projects.len())),
+        0 => cli::warn(&format!("{} warning(s), no errors", warnings)),
+        _ => return Err(format!("{} error(s) and {} warning(s) found", errors, warnings)),
+    }
+    Ok(())
+}
+
 // ── Project ─────────────────────────────────────────────────────────────────
 
 pub fn execute_project_command(cmd: ProjectCommand) -> Result<(), String> {
@@ -289,8 +915,23 @@ pub fn execute_project_command(cmd: ProjectCommand) -> Result<(), String> {
                 cli::warn("No active project set. Use 'lazydraft project switch <name>' to select one.");
             }
         }
-        ProjectCommand::Create { name, description } => {
-            let project = project_manager.create_project(&name, description)?;
+        ProjectCommand::Create {
+            name,
+            description,
+            preset,
+            site,
+        } => {
+            let mut project = ProjectConfig::new(name.clone(), description);
+            if let Some(preset) = preset {
+                let preset = Preset::parse(&preset)?;
+                let site_root = match site {
+                    Some(site) => PathBuf::from(expand_tilde(&site)),
+                    None => env::current_dir().map_err(|e| format!("Cannot read current directory: {}", e))?,
+                };
+                preset.apply(&mut project.config, &site_root);
+                cli::info(&format!("Applied the {} preset for the site at {}", preset.name(), site_root.display()));
+            }
+            let project = project_manager.create_project(project)?;
             cli::success(&format!("Created project '{}'", project.name));
 
             let projects = project_manager.list_projects()?;
@@ -342,8 +983,12 @@ pub fn execute_project_command(cmd: ProjectCommand) -> Result<(), String> {
         ProjectCommand::Rename { old_name, new_name } => {
             let mut project = project_manager.load_project(&old_name)?;
             project.name = new_name.clone();
+            let ledger = project_manager.load_staging_ledger(&old_name)?;
 
             project_manager.save_project(&project)?;
+            if !ledger.entries.is_empty() {
+                project_manager.save_staging_ledger(&new_name, &ledger)?;
+            }
             project_manager.delete_project(&old_name)?;
 
             if let Some(active) = project_manager.get_active_project()? {
@@ -388,15 +1033,29 @@ fn print_config_summary(config: &Config) {
     cli::kv("Source Assets", config.get_source_asset_dir().unwrap_or_else(|| "not set".to_string()));
     cli::kv("Target Assets", config.get_target_asset_dir().unwrap_or_else(|| "not set".to_string()));
 
+    match build_pipeline(config) {
+        Ok(pipeline) => {
+            let steps: Vec<&str> = pipeline.iter().map(|t| t.name()).collect();
+            let label = if config.pipeline.is_some() { "Pipeline" } else { "Pipeline (legacy)" };
+            cli::kv(label, if steps.is_empty() { "none".to_string() } else { steps.join(" -> ") });
+        }
+        Err(e) => cli::kv("Pipeline", format!("invalid: {}", e)),
+    }
+
     let mut features = Vec::new();
-    if config.sanitize_frontmatter.unwrap_or(false) { features.push("sanitize frontmatter"); }
-    if config.auto_add_cover_img.unwrap_or(false) { features.push("auto cover image"); }
-    if config.auto_add_hero_img.unwrap_or(false) { features.push("auto hero image"); }
-    if config.remove_draft_on_stage.unwrap_or(false) { features.push("remove draft on stage"); }
     if config.add_date_prefix.unwrap_or(false) { features.push("date prefix"); }
-    if config.remove_wikilinks.unwrap_or(false) { features.push("remove wikilinks"); }
-    if config.trim_tags.unwrap_or(false) { features.push("trim tags"); }
     if config.use_mdx_format.unwrap_or(false) { features.push("MDX format"); }
+    if config.asset_discovery == Some(AssetDiscovery::References) { features.push("reference-based assets"); }
+    if config.discovery.is_some() { features.push("custom discovery"); }
+    if config.filename_template.is_some() { features.push("filename template"); }
+    if config.is_bundle_layout() { features.push("bundle layout"); }
+    if config.images.is_some() { features.push("image processing"); }
+    match config.frontmatter_format() {
+        Some(FrontmatterFormat::Yaml) => features.push("YAML frontmatter"),
+        Some(FrontmatterFormat::Toml) => features.push("TOML frontmatter"),
+        Some(FrontmatterFormat::Json) => features.push("JSON frontmatter"),
+        None => {}
+    }
 
     if !features.is_empty() {
         cli::kv("Features", features.join(", "));
@@ -1,6 +1,11 @@
+use std::collections::BTreeMap;
 use std::env;
 use serde::{Deserialize, Serialize};
 
+use crate::document::FrontmatterFormat;
+use crate::imaging::ImageOptions;
+use crate::transform::PipelineStep;
+
 pub type ConfigResult<T> = Result<T, String>;
 
 // Helper function to expand tilde in paths
@@ -40,6 +45,8 @@ pub struct Config {
     #[serde(default)]
     pub add_date_prefix: Option<bool>,
     #[serde(default)]
+    pub filename_template: Option<String>,
+    #[serde(default)]
     pub remove_wikilinks: Option<bool>,
     #[serde(default)]
     pub trim_tags: Option<bool>,
@@ -47,6 +54,81 @@ pub struct Config {
     pub tag_prefix: Option<String>,
     #[serde(default)]
     pub use_mdx_format: Option<bool>,
+    #[serde(default)]
+    pub asset_discovery: Option<AssetDiscovery>,
+    #[serde(default)]
+    pub layout: Option<OutputLayout>,
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
+    #[serde(default)]
+    pub pipeline: Option<Vec<PipelineStep>>,
+    #[serde(default)]
+    pub discovery: Option<DiscoveryConfig>,
+    #[serde(default)]
+    pub frontmatter: Option<FrontmatterConfig>,
+    #[serde(default)]
+    pub images: Option<ImageOptions>,
+    /// Name of the generator preset the project was created with
+    #[serde(default)]
+    pub preset: Option<String>,
+}
+
+/// How the assets of a writing are found in `source_asset_dir`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetDiscovery {
+    /// Every file whose name contains the writing's `yaml_asset_prefix` value
+    #[default]
+    Prefix,
+    /// Only the files the writing embeds or links to
+    References,
+}
+
+/// How staged writings are arranged in `target_dir`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputLayout {
+    /// `target_dir/<name>.md`, assets in `target_asset_dir`
+    #[default]
+    Flat,
+    /// `target_dir/<slug>/index.md` with the assets next to it
+    Bundle,
+}
+
+/// Which files in `source_dir` count as writings
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct DiscoveryConfig {
+    /// Note file extensions without the dot, `["md"]` when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// Globs relative to `source_dir`; when set, only matching notes are writings
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Globs relative to `source_dir` for notes and folders to skip. Hidden
+    /// files and folders when not set; an empty list excludes nothing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct FrontmatterConfig {
+    /// Format of the staged frontmatter; defaults to the format of each source note
+    #[serde(default)]
+    pub format: Option<FrontmatterFormat>,
+    #[serde(default)]
+    pub map: FrontmatterMap,
+}
+
+/// Frontmatter key names, keyed by the name LazyDraft uses internally
+/// (`title`, `draft`, `publishDate`, `assetPrefix`, `image`, `heroImage`, `tags`)
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct FrontmatterMap {
+    /// Keys as they appear in the source notes, e.g. `publishDate = "created"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub source: BTreeMap<String, String>,
+    /// Keys to write in the staged output, e.g. `publishDate = "pubDatetime"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub target: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -82,28 +164,48 @@ impl Config {
         self.target_asset_dir.as_ref().map(|s| expand_tilde(s))
     }
 
+    /// Frontmatter key mapping, empty when the project does not define one
+    pub fn frontmatter_map(&self) -> FrontmatterMap {
+        self.frontmatter.as_ref().map(|f| f.map.clone()).unwrap_or_default()
+    }
+
+    pub fn is_bundle_layout(&self) -> bool {
+        self.layout.unwrap_or_default() == OutputLayout::Bundle
+    }
+
+    /// Output frontmatter format, `None` to keep each note's own format
+    pub fn frontmatter_format(&self) -> Option<FrontmatterFormat> {
+        self.frontmatter.as_ref().and_then(|f| f.format)
+    }
+
     /// Check if any required fields are empty
     pub fn has_empty_fields(&self) -> Option<String> {
-        if self.source_dir.as_ref().map_or(true, |s| s.is_empty()) {
+        if self.source_dir.as_ref().is_none_or(|s| s.is_empty()) {
             return Some("source_dir".to_string());
         }
-        if self.source_asset_dir.as_ref().map_or(true, |s| s.is_empty()) {
+        if self.source_asset_dir.as_ref().is_none_or(|s| s.is_empty()) {
             return Some("source_asset_dir".to_string());
         }
-        if self.target_dir.as_ref().map_or(true, |s| s.is_empty()) {
+        if self.target_dir.as_ref().is_none_or(|s| s.is_empty()) {
             return Some("target_dir".to_string());
         }
-        if self.target_asset_dir.as_ref().map_or(true, |s| s.is_empty()) {
-            return Some("target_asset_dir".to_string());
-        }
-        if self.target_asset_prefix.as_ref().map_or(true, |s| s.is_empty()) {
-            return Some("target_asset_prefix".to_string());
+        // Bundles keep their assets next to the post
+        if !self.is_bundle_layout() {
+            if self.target_asset_dir.as_ref().is_none_or(|s| s.is_empty()) {
+                return Some("target_asset_dir".to_string());
+            }
+            if self.target_asset_prefix.as_ref().is_none_or(|s| s.is_empty()) {
+                return Some("target_asset_prefix".to_string());
+            }
         }
-        if self.yaml_asset_prefix.as_ref().map_or(true, |s| s.is_empty()) {
+        if self.asset_discovery.unwrap_or_default() == AssetDiscovery::Prefix
+            && self.yaml_asset_prefix.as_ref().is_none_or(|s| s.is_empty())
+        {
             return Some("yaml_asset_prefix".to_string());
         }
-        if self.trim_tags.unwrap_or(false)
-            && self.tag_prefix.as_ref().map_or(true, |s| s.is_empty())
+        if self.pipeline.is_none()
+            && self.trim_tags.unwrap_or(false)
+            && self.tag_prefix.as_ref().is_none_or(|s| s.is_empty())
         {
             return Some("tag_prefix".to_string());
         }
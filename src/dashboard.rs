@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::io;
 use std::time::{Duration, Instant};
 use std::sync::mpsc;
@@ -15,9 +16,13 @@ use ratatui::{
 };
 use notify::{RecursiveMode, RecommendedWatcher, Watcher, Event as NotifyEvent, EventKind};
 
+use crate::discovery::Discovery;
+use crate::watch::{Debouncer, SourceChange};
 use crate::project::{get_project_manager, ProjectConfig, ProjectManager};
-use crate::writing::{create_writing_list, Writing, update_writing_content_and_transfer};
-use crate::asset::{get_asset_list_of_writing, transfer_asset_files};
+use crate::links::LinkResolver;
+use crate::staging::{stage_files, StagedEntry, StagingLedger};
+use crate::writing::{create_writing_list, scan_writings, Writing};
+use crate::asset::get_asset_list_of_writing;
 use crate::views;
 
 #[derive(Clone, PartialEq)]
@@ -46,7 +51,7 @@ pub struct Dashboard {
     pub writings: Vec<Writing>,
     pub selected_writings_index: usize,
     pub popup_type: PopupType,
-    pub staged_writings: Vec<String>,
+    pub staging_ledgers: HashMap<String, StagingLedger>,
     pub auto_stage_enabled: bool,
     pub popup_timestamp: Option<Instant>,
     pub last_message: Option<(String, bool, Instant)>,
@@ -55,6 +60,10 @@ pub struct Dashboard {
     // File watching — private
     file_watcher: Option<RecommendedWatcher>,
     file_events_rx: Option<mpsc::Receiver<notify::Result<NotifyEvent>>>,
+    /// Pending restages per project, released once the file is quiet
+    debouncers: HashMap<String, Debouncer>,
+    /// Writing discovery per project, built on first use
+    discoveries: HashMap<String, Discovery>,
 }
 
 #[derive(Clone)]
@@ -71,6 +80,7 @@ impl Dashboard {
         let project_manager = get_project_manager()?;
         let projects = project_manager.list_projects()?;
         let active_project = project_manager.get_active_project()?;
+        let staging_ledgers = load_staging_ledgers(&project_manager, &projects);
 
         let mut dashboard = Self {
             project_manager,
@@ -84,9 +94,11 @@ impl Dashboard {
             writings: Vec::new(),
             selected_writings_index: 0,
             popup_type: PopupType::None,
-            staged_writings: Vec::new(),
+            staging_ledgers,
             file_watcher: None,
             file_events_rx: None,
+            debouncers: HashMap::new(),
+            discoveries: HashMap::new(),
             auto_stage_enabled: true,
             popup_timestamp: None,
             last_message: None,
@@ -94,9 +106,45 @@ impl Dashboard {
         };
 
         dashboard.update_project_stats()?;
+        dashboard.rearm_staged_watches();
         Ok(dashboard)
     }
 
+    /// Watch every writing recorded in the staging ledgers of a previous session
+    fn rearm_staged_watches(&mut self) {
+        let staged_paths: Vec<String> = self
+            .staging_ledgers
+            .values()
+            .flat_map(|ledger| ledger.entries.iter())
+            .map(|entry| entry.source_path.clone())
+            .filter(|path| Path::new(path).exists())
+            .collect();
+        for path in staged_paths {
+            let _ = self.add_file_to_watch(&path);
+        }
+    }
+
+    pub fn is_staged(&self, writing_path: &str) -> bool {
+        self.staging_ledgers
+            .values()
+            .any(|ledger| ledger.contains(writing_path))
+    }
+
+    /// Number of staged writings in the selected project
+    pub fn staged_count(&self) -> usize {
+        self.projects
+            .get(self.selected_index)
+            .and_then(|project| self.staging_ledgers.get(&project.name))
+            .map_or(0, |ledger| ledger.entries.len())
+    }
+
+    fn persist_staging_ledger(&self, project_name: &str) -> Result<(), String> {
+        match self.staging_ledgers.get(project_name) {
+            Some(ledger) => self.project_manager.save_staging_ledger(project_name, ledger),
+            None => Ok(()),
+        }
+    }
+
     pub fn update_project_stats(&mut self) -> Result<(), String> {
         self.project_stats.clear();
 
@@ -158,6 +206,14 @@ impl Dashboard {
     pub fn refresh_data(&mut self) -> Result<(), String> {
         self.projects = self.project_manager.list_projects()?;
         self.active_project = self.project_manager.get_active_project()?;
+        // Discovery settings may have changed with the project files
+        self.discoveries.clear();
+        for project in &self.projects {
+            if !self.staging_ledgers.contains_key(&project.name) {
+                let ledger = self.project_manager.load_staging_ledger(&project.name).unwrap_or_default();
+                self.staging_ledgers.insert(project.name.clone(), ledger);
+            }
+        }
         self.update_project_stats()?;
         self.selected_index = self.selected_index.min(self.projects.len().saturating_sub(1));
         self.last_update = Instant::now();
@@ -167,9 +223,9 @@ impl Dashboard {
     fn load_writings_for_selected_project(&mut self) -> Result<(), String> {
         if let Some(project) = self.projects.get(self.selected_index) {
             if project.config.get_source_dir().is_some() {
-                match create_writing_list(&project.config) {
-                    Ok(writings) => {
-                        self.writings = writings;
+                match scan_writings(&project.config) {
+                    Ok(scan) => {
+                        self.writings = scan.with_broken();
                         self.selected_writings_index = 0;
                     }
                     Err(e) => return Err(format!("Failed to load writings: {}", e)),
@@ -211,7 +267,7 @@ impl Dashboard {
     pub fn show_stage_confirm_popup(&mut self) {
         if self.view_mode == ViewMode::Writings && !self.writings.is_empty() {
             if let Some(writing) = self.writings.get(self.selected_writings_index) {
-                if writing.is_draft && !self.staged_writings.contains(&writing.path) {
+                if writing.is_draft && !self.is_staged(&writing.path) {
                     self.show_popup(PopupType::StageConfirm);
                 }
             }
@@ -221,7 +277,7 @@ impl Dashboard {
     pub fn show_revert_confirm_popup(&mut self) {
         if self.view_mode == ViewMode::Writings && !self.writings.is_empty() {
             if let Some(writing) = self.writings.get(self.selected_writings_index) {
-                if self.staged_writings.contains(&writing.path) {
+                if self.is_staged(&writing.path) {
                     self.show_popup(PopupType::RevertConfirm);
                 }
             }
@@ -230,31 +286,45 @@ impl Dashboard {
 
     pub fn stage_selected_writing(&mut self) -> Result<(), String> {
         if let Some(writing) = self.writings.get(self.selected_writings_index).cloned() {
-            if let Some(project) = self.projects.get(self.selected_index) {
+            if let Some(diagnostic) = writing.diagnostic.as_ref().filter(|_| writing.is_broken()) {
+                self.show_popup(PopupType::OperationResult {
+                    success: false,
+                    message: format!("Cannot stage: {}", diagnostic),
+                });
+                return Err(format!("Cannot stage {}: {}", writing.title, diagnostic.message));
+            }
+            if let Some(project) = self.projects.get(self.selected_index).cloned() {
                 let asset_list = get_asset_list_of_writing(&writing, &project.config)
                     .map_err(|e| format!("Failed to create asset list: {}", e))?;
-                let asset_count = asset_list.len();
-
-                match update_writing_content_and_transfer(&project.config, &writing, &asset_list) {
-                    Ok(_) => {
-                        let asset_result = if asset_count > 0 {
-                            match transfer_asset_files(&project.config, &asset_list) {
-                                Ok(_) => format!(" ({} assets transferred)", asset_count),
-                                Err(e) => format!(" (Warning: Asset transfer failed: {})", e),
-                            }
-                        } else {
-                            " (no assets found)".to_string()
-                        };
+                let no_assets = if asset_list.is_empty() { " (no assets found)" } else { "" };
 
+                let links = LinkResolver::new(self.writings.iter().filter(|w| !w.is_broken()));
+                match stage_files(&project.config, &writing, &asset_list, &links) {
+                    Ok(output) => {
                         let writing_path = writing.path.clone();
                         let writing_title = writing.title.clone();
-                        self.staged_writings.push(writing_path.clone());
+                        let mut warnings = output.rendered.warnings.clone();
+                        warnings.extend(self.record_staged(
+                            &project.name,
+                            StagedEntry::new(
+                                &writing_path,
+                                &output.rendered,
+                                &output.asset_files,
+                                &output.overwritten_assets,
+                            ),
+                        ));
                         let _ = self.add_file_to_watch(&writing_path);
                         let _ = self.load_writings_for_selected_project();
 
                         self.show_popup(PopupType::OperationResult {
                             success: true,
-                            message: format!("Successfully staged: {}{}", writing_title, asset_result),
+                            message: format!(
+                                "Successfully staged: {} ({}){}{}",
+                                writing_title,
+                                output.stats,
+                                no_assets,
+                                warnings_summary(&warnings)
+                            ),
                         });
                         Ok(())
                     }
@@ -276,17 +346,37 @@ impl Dashboard {
 
     pub fn revert_selected_writing(&mut self) -> Result<(), String> {
         if let Some(writing) = self.writings.get(self.selected_writings_index).cloned() {
-            if let Some(pos) = self.staged_writings.iter().position(|x| x == &writing.path) {
+            if let Some(project_name) = self.ledger_owner(&writing.path) {
                 let writing_path = writing.path.clone();
                 let writing_title = writing.title.clone();
-                self.staged_writings.remove(pos);
-                let _ = self.remove_file_from_watch(&writing_path);
+                let result = match self.staging_ledgers.get_mut(&project_name) {
+                    Some(ledger) => ledger.unstage(&writing_path, false),
+                    None => Err("Writing is not staged".to_string()),
+                };
 
-                self.show_popup(PopupType::OperationResult {
-                    success: true,
-                    message: format!("Reverted staging for: {}", writing_title),
-                });
-                Ok(())
+                match result {
+                    Ok(removed) => {
+                        let _ = self.persist_staging_ledger(&project_name);
+                        let _ = self.remove_file_from_watch(&writing_path);
+
+                        self.show_popup(PopupType::OperationResult {
+                            success: true,
+                            message: format!(
+                                "Reverted staging for: {} ({} files removed)",
+                                writing_title,
+                                removed.len()
+                            ),
+                        });
+                        Ok(())
+                    }
+                    Err(e) => {
+                        self.show_popup(PopupType::OperationResult {
+                            success: false,
+                            message: format!("Failed to revert: {}", e),
+                        });
+                        Err(e)
+                    }
+                }
             } else {
                 Err("Writing is not staged".to_string())
             }
@@ -295,6 +385,27 @@ impl Dashboard {
         }
     }
 
+    /// Name of the project whose staging ledger contains the writing
+    fn ledger_owner(&self, writing_path: &str) -> Option<String> {
+        self.staging_ledgers
+            .iter()
+            .find(|(_, ledger)| ledger.contains(writing_path))
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Record a stage and return warnings about previous output left in place
+    fn record_staged(&mut self, project_name: &str, entry: StagedEntry) -> Vec<String> {
+        let stale = self
+            .staging_ledgers
+            .entry(project_name.to_string())
+            .or_default()
+            .record(entry);
+        if let Err(e) = self.persist_staging_ledger(project_name) {
+            self.last_message = Some((e, false, Instant::now()));
+        }
+        stale.warnings().collect()
+    }
+
     pub fn close_popup(&mut self) {
         self.popup_type = PopupType::None;
         self.popup_timestamp = None;
@@ -337,8 +448,8 @@ impl Dashboard {
 
         match RecommendedWatcher::new(tx, notify::Config::default()) {
             Ok(mut watcher) => {
-                for writing_path in &self.staged_writings {
-                    let _ = watcher.watch(Path::new(writing_path), RecursiveMode::NonRecursive);
+                for entry in self.staging_ledgers.values().flat_map(|l| l.entries.iter()) {
+                    let _ = watcher.watch(Path::new(&entry.source_path), RecursiveMode::NonRecursive);
                 }
                 self.file_watcher = Some(watcher);
                 self.file_events_rx = Some(rx);
@@ -371,57 +482,124 @@ impl Dashboard {
     }
 
     pub fn process_file_events(&mut self) {
-        let mut paths_to_restage = Vec::new();
+        let mut changed_paths = Vec::new();
 
         if let Some(ref rx) = self.file_events_rx {
             while let Ok(event_result) = rx.try_recv() {
-                match event_result {
-                    Ok(event) => {
-                        if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
-                            if self.auto_stage_enabled {
-                                for path in event.paths {
-                                    let path_str = path.to_string_lossy().to_string();
-                                    if self.staged_writings.contains(&path_str) {
-                                        paths_to_restage.push(path_str);
-                                    }
-                                }
-                            }
-                        }
+                if let Ok(event) = event_result {
+                    if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+                        && self.auto_stage_enabled
+                    {
+                        changed_paths.extend(event.paths);
                     }
-                    Err(_) => {}
                 }
             }
         }
 
-        for path_str in paths_to_restage {
+        for path in changed_paths {
+            let path_str = path.to_string_lossy().to_string();
+            let Some(project_name) = self.ledger_owner(&path_str) else {
+                continue;
+            };
+            let Some(project) = self.projects.iter().find(|p| p.name == project_name) else {
+                continue;
+            };
+            self.debouncers
+                .entry(project_name)
+                .or_insert_with(|| Debouncer::for_config(&project.config))
+                .push(SourceChange::Changed(path));
+        }
+
+        if !self.auto_stage_enabled {
+            self.debouncers.clear();
+            return;
+        }
+        let ready: Vec<String> = self
+            .debouncers
+            .values_mut()
+            .flat_map(|debouncer| debouncer.take_ready())
+            .filter_map(|change| match change {
+                SourceChange::Changed(path) => Some(path.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        for path_str in ready {
             self.auto_restage_writing(&path_str);
         }
     }
 
     fn auto_restage_writing(&mut self, file_path: &str) {
-        if let Some(writing) = self.writings.iter().find(|w| w.path == file_path).cloned() {
-            if let Some(project) = self.projects.get(self.selected_index) {
-                match get_asset_list_of_writing(&writing, &project.config) {
-                    Ok(asset_list) => {
-                        match update_writing_content_and_transfer(&project.config, &writing, &asset_list) {
-                            Ok(_) => {
-                                let _ = transfer_asset_files(&project.config, &asset_list);
-                                let _ = self.load_writings_for_selected_project();
-                                self.show_popup(PopupType::OperationResult {
-                                    success: true,
-                                    message: format!("Auto-staged: {}", writing.title),
-                                });
-                            }
-                            Err(_) => {}
-                        }
-                    }
-                    Err(_) => {}
+        let Some(project_name) = self.ledger_owner(file_path) else {
+            return;
+        };
+        let Some(project) = self.projects.iter().find(|p| p.name == project_name).cloned() else {
+            return;
+        };
+        // Notes excluded since they were staged are no longer followed
+        if !self.discoveries.contains_key(&project.name) {
+            let Ok(discovery) = Discovery::new(&project.config) else {
+                return;
+            };
+            self.discoveries.insert(project.name.clone(), discovery);
+        }
+        if !self.discoveries[&project.name].is_writing(Path::new(file_path)) {
+            return;
+        }
+        // The loaded list belongs to the selected project, which may not own this note
+        let writings = if self.writings.iter().any(|w| w.path == file_path) {
+            self.writings.iter().filter(|w| !w.is_broken()).cloned().collect()
+        } else {
+            create_writing_list(&project.config).unwrap_or_default()
+        };
+        let links = LinkResolver::new(&writings);
+
+        if let Some(writing) = writings.iter().find(|w| w.path == file_path) {
+            if let Ok(asset_list) = get_asset_list_of_writing(writing, &project.config) {
+                if let Ok(output) = stage_files(&project.config, writing, &asset_list, &links) {
+                    let rendered = output.rendered;
+                    let entry = StagedEntry::new(
+                        &writing.path,
+                        &rendered,
+                        &output.asset_files,
+                        &output.overwritten_assets,
+                    );
+                    let mut warnings = rendered.warnings.clone();
+                    warnings.extend(self.record_staged(&project.name, entry));
+                    let _ = self.load_writings_for_selected_project();
+                    self.show_popup(PopupType::OperationResult {
+                        success: true,
+                        message: format!("Auto-staged: {}{}", writing.title, warnings_summary(&warnings)),
+                    });
                 }
             }
         }
     }
 }
 
+/// Short suffix for result popups listing the first staging warning
+fn warnings_summary(warnings: &[String]) -> String {
+    match warnings {
+        [] => String::new(),
+        [only] => format!(" (warning: {})", only),
+        [first, rest @ ..] => format!(" ({} warnings, first: {})", rest.len() + 1, first),
+    }
+}
+
+fn load_staging_ledgers(
+    project_manager: &ProjectManager,
+    projects: &[ProjectConfig],
+) -> HashMap<String, StagingLedger> {
+    projects
+        .iter()
+        .map(|project| {
+            let ledger = project_manager
+                .load_staging_ledger(&project.name)
+                .unwrap_or_default();
+            (project.name.clone(), ledger)
+        })
+        .collect()
+}
+
 // ── Dashboard entry point ───────────────────────────────────────────────────
 
 pub fn run_dashboard() -> Result<(), Box<dyn std::error::Error>> {
@@ -539,20 +717,14 @@ fn run_app<B: Backend>(
                                 ViewMode::Writings => dashboard.previous_writing(),
                             }
                         }
-                        KeyCode::Right => {
-                            if dashboard.view_mode == ViewMode::Projects {
-                                let _ = dashboard.switch_to_writings_view();
-                            }
+                        KeyCode::Right if dashboard.view_mode == ViewMode::Projects => {
+                            let _ = dashboard.switch_to_writings_view();
                         }
-                        KeyCode::Left => {
-                            if dashboard.view_mode == ViewMode::Writings {
-                                dashboard.switch_to_projects_view();
-                            }
+                        KeyCode::Left if dashboard.view_mode == ViewMode::Writings => {
+                            dashboard.switch_to_projects_view();
                         }
-                        KeyCode::Enter | KeyCode::Char(' ') => {
-                            if dashboard.view_mode == ViewMode::Projects {
-                                let _ = dashboard.switch_to_selected_project();
-                            }
+                        KeyCode::Enter | KeyCode::Char(' ') if dashboard.view_mode == ViewMode::Projects => {
+                            let _ = dashboard.switch_to_selected_project();
                         }
                         _ => {}
                     }
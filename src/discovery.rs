@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use walkdir::WalkDir;
+
+use crate::config::Config;
+
+/// Gitignore-style file in the root of `source_dir`
+pub const IGNORE_FILE: &str = ".lazydraftignore";
+
+const DEFAULT_EXTENSIONS: &[&str] = &["md"];
+
+/// Dot files and folders such as `.obsidian` and `.trash`, at any depth
+const DEFAULT_EXCLUDE: &[&str] = &["**/.*"];
+
+/// Decides which files in `source_dir` are writings. Shared by the writing
+/// list, the continuous watcher and the dashboard so they always agree.
+pub struct Discovery {
+    root: PathBuf,
+    extensions: Vec<String>,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    ignore: Gitignore,
+}
+
+impl Discovery {
+    pub fn new(config: &Config) -> Result<Self, String> {
+        let root = PathBuf::from(config.get_source_dir().ok_or("source dir should be set")?);
+        let settings = config.discovery.clone().unwrap_or_default();
+
+        let extensions = if settings.extensions.is_empty() {
+            DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect()
+        } else {
+            settings
+                .extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect()
+        };
+        let include = if settings.include.is_empty() {
+            None
+        } else {
+            Some(build_globs(&settings.include, "include")?)
+        };
+        let exclude = match &settings.exclude {
+            Some(patterns) => build_globs(patterns, "exclude")?,
+            None => build_globs(DEFAULT_EXCLUDE, "exclude")?,
+        };
+
+        let mut builder = GitignoreBuilder::new(&root);
+        let ignore_file = root.join(IGNORE_FILE);
+        if ignore_file.is_file() {
+            if let Some(e) = builder.add(&ignore_file) {
+                return Err(format!("Invalid {}: {}", IGNORE_FILE, e));
+            }
+        }
+        let ignore = builder
+            .build()
+            .map_err(|e| format!("Invalid {}: {}", IGNORE_FILE, e))?;
+
+        Ok(Discovery {
+            root,
+            extensions,
+            include,
+            exclude,
+            ignore,
+        })
+    }
+
+    /// Whether `path` is a writing, judged from the path alone
+    pub fn is_writing(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let has_extension = path
+            .extension()
+            .is_some_and(|e| self.extensions.contains(&e.to_string_lossy().to_lowercase()));
+        has_extension
+            && !self.is_ignored(relative, false)
+            && self.include.as_ref().is_none_or(|globs| globs.is_match(relative))
+    }
+
+    /// Every writing under `source_dir`. Ignored folders are not descended into.
+    pub fn walk(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|entry| {
+                let Ok(relative) = entry.path().strip_prefix(&self.root) else {
+                    return false;
+                };
+                if relative.as_os_str().is_empty() {
+                    return true;
+                }
+                !(entry.file_type().is_dir() && self.is_ignored(relative, true))
+            })
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.file_type().is_file() && self.is_writing(entry.path()))
+            .map(|entry| entry.into_path())
+    }
+
+    fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        // An excluded folder excludes everything in it, as in the walk
+        let excluded = relative
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| self.exclude.is_match(p));
+        excluded
+            || self
+                .ignore
+                .matched_path_or_any_parents(relative, is_dir)
+                .is_ignore()
+    }
+}
+
+fn build_globs(patterns: &[impl AsRef<str>], field: &str) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.iter().map(AsRef::as_ref) {
+        let glob = Glob::new(pattern)
+            .map_err(|e| format!("Invalid discovery.{} pattern '{}': {}", field, pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Invalid discovery.{} patterns: {}", field, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A vault with notes in plain, excluded and hidden folders
+    fn vault(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("lazydraft-discovery-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for file in [
+            "post.md",
+            "post.markdown",
+            "drafts/idea.md",
+            "templates/daily.md",
+            "drawing.excalidraw.md",
+            ".obsidian/workspace.md",
+            "notes/.trash/old.md",
+            "image.png",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "# note").unwrap();
+        }
+        root
+    }
+
+    fn discovery(root: &Path, settings: &str) -> Discovery {
+        let config: Config = toml::from_str(&format!(
+            "source_dir = {:?}\n[discovery]\n{}",
+            root.display().to_string(),
+            settings
+        ))
+        .unwrap();
+        Discovery::new(&config).unwrap()
+    }
+
+    fn writings(root: &Path, discovery: &Discovery) -> Vec<String> {
+        let mut found: Vec<String> = discovery
+            .walk()
+            .map(|path| path.strip_prefix(root).unwrap().display().to_string())
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn skips_hidden_folders_by_default() {
+        let root = vault("default");
+        let discovery = discovery(&root, "");
+        assert_eq!(
+            writings(&root, &discovery),
+            vec!["drafts/idea.md", "drawing.excalidraw.md", "post.md", "templates/daily.md"]
+        );
+        assert!(!discovery.is_writing(&root.join(".obsidian/workspace.md")));
+        assert!(!discovery.is_writing(&root.join("notes/.trash/old.md")));
+        assert!(!discovery.is_writing(Path::new("/elsewhere/post.md")));
+    }
+
+    #[test]
+    fn exclude_replaces_the_default() {
+        let root = vault("exclude");
+        let discovery = discovery(&root, r#"exclude = ["templates", "**/*.excalidraw.md"]"#);
+        assert_eq!(
+            writings(&root, &discovery),
+            vec![".obsidian/workspace.md", "drafts/idea.md", "notes/.trash/old.md", "post.md"]
+        );
+        assert!(!discovery.is_writing(&root.join("templates/daily.md")));
+    }
+
+    #[test]
+    fn include_and_extensions() {
+        let root = vault("include");
+        let discovery = discovery(&root, "extensions = [\"md\", \".Markdown\"]\ninclude = [\"post.*\"]");
+        assert_eq!(writings(&root, &discovery), vec!["post.markdown", "post.md"]);
+    }
+
+    #[test]
+    fn reads_the_ignore_file() {
+        let root = vault("ignore");
+        fs::write(root.join(IGNORE_FILE), "drafts/\n*.excalidraw.md\n").unwrap();
+        let discovery = discovery(&root, "");
+        assert_eq!(writings(&root, &discovery), vec!["post.md", "templates/daily.md"]);
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        let root = vault("invalid");
+        let config: Config = toml::from_str(&format!(
+            "source_dir = {:?}\n[discovery]\nexclude = [\"[\"]",
+            root.display().to_string()
+        ))
+        .unwrap();
+        let error = Discovery::new(&config).err().unwrap();
+        assert!(error.starts_with("Invalid discovery.exclude pattern '['"));
+    }
+}
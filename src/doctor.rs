@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+
+use crate::cli;
+use crate::config::Config;
+use crate::discovery::{Discovery, IGNORE_FILE};
+use crate::transform;
+use crate::writing::{scan_writings, Writing};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem found in a project, with a suggested fix
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub fix: String,
+}
+
+impl Finding {
+    fn error(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Error,
+            message: message.into(),
+            fix: fix.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Finding {
+            severity: Severity::Warning,
+            message: message.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+/// Run every check against a project's configuration and notes
+pub fn check_project(config: &Config) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if let Some(field) = config.has_empty_fields() {
+        findings.push(Finding::error(
+            format!("'{}' is not set", field),
+            format!("Set '{}' in the project file with: lazydraft config --edit", field),
+        ));
+    }
+    if let Err(e) = transform::build_pipeline(config) {
+        findings.push(Finding::error(
+            format!("Invalid pipeline: {}", e),
+            "Fix the 'pipeline' entry in the project TOML",
+        ));
+    }
+
+    if let Some(Err(e)) = config.get_source_dir().map(|_| Discovery::new(config)) {
+        findings.push(Finding::error(
+            e,
+            format!("Fix the [discovery] table or {} in source_dir", IGNORE_FILE),
+        ));
+    }
+
+    let dirs = configured_dirs(config);
+    for (field, path) in &dirs {
+        check_dir(field, path, &mut findings);
+    }
+    check_overlap(&dirs, &mut findings);
+
+    if config.get_source_dir().is_some_and(|dir| Path::new(&dir).is_dir()) {
+        check_notes(config, &mut findings);
+    }
+    findings
+}
+
+fn configured_dirs(config: &Config) -> Vec<(&'static str, PathBuf)> {
+    let mut dirs = vec![
+        ("source_dir", config.get_source_dir()),
+        ("source_asset_dir", config.get_source_asset_dir()),
+        ("target_dir", config.get_target_dir()),
+    ];
+    if !config.is_bundle_layout() {
+        dirs.push(("target_asset_dir", config.get_target_asset_dir()));
+    }
+    dirs.into_iter()
+        .filter_map(|(field, dir)| dir.filter(|d| !d.is_empty()).map(|d| (field, PathBuf::from(d))))
+        .collect()
+}
+
+fn check_dir(field: &str, path: &Path, findings: &mut Vec<Finding>) {
+    if !path.exists() {
+        findings.push(Finding::error(
+            format!("{} does not exist: {}", field, path.display()),
+            format!("Create it with: mkdir -p '{}'", path.display()),
+        ));
+        return;
+    }
+    if !path.is_dir() {
+        findings.push(Finding::error(
+            format!("{} is not a directory: {}", field, path.display()),
+            format!("Point {} at a directory", field),
+        ));
+        return;
+    }
+    // Staging and publishing write to these, so actually try it
+    let probe = path.join(".lazydraft-doctor");
+    match fs::write(&probe, b"") {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+        }
+        Err(e) => findings.push(Finding::error(
+            format!("{} is not writable: {} ({})", field, path.display(), e),
+            format!("Check the permissions of '{}'", path.display()),
+        )),
+    }
+}
+
+/// Source and target directories must not be the same or inside each other,
+/// or staged output would be picked up as notes and assets
+fn check_overlap(dirs: &[(&'static str, PathBuf)], findings: &mut Vec<Finding>) {
+    let resolved: Vec<(&str, PathBuf)> = dirs
+        .iter()
+        .map(|(field, path)| (*field, fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+        .collect();
+    let sources = resolved.iter().filter(|(field, _)| field.starts_with("source"));
+    for (source_field, source) in sources {
+        for (target_field, target) in resolved.iter().filter(|(field, _)| field.starts_with("target")) {
+            let problem = if source == target {
+                "is the same directory as"
+            } else if target.starts_with(source) {
+                "contains"
+            } else if source.starts_with(target) {
+                "is inside"
+            } else {
+                continue;
+            };
+            findings.push(Finding::error(
+                format!("{} {} {}", source_field, problem, target_field),
+                "Keep the vault and the site in separate directories",
+            ));
+        }
+    }
+}
+
+fn check_notes(config: &Config, findings: &mut Vec<Finding>) {
+    let Ok(scan) = scan_writings(config) else {
+        return;
+    };
+    for diagnostic in &scan.diagnostics {
+        if diagnostic.skipped {
+            findings.push(Finding::error(
+                diagnostic.to_string(),
+                "Fix the frontmatter; the note is skipped until then",
+            ));
+        } else {
+            findings.push(Finding::warning(
+                diagnostic.to_string(),
+                "Use the YYYY-MM-DD format; the date is ignored for sorting, filters and file names",
+            ));
+        }
+    }
+
+    let writings = scan.writings;
+    let mut by_title: HashMap<String, Vec<&Writing>> = HashMap::new();
+    for writing in writings.iter().filter(|w| !w.title.is_empty()) {
+        by_title.entry(writing.title.to_lowercase()).or_default().push(writing);
+    }
+    let mut duplicates: Vec<_> = by_title.into_values().filter(|group| group.len() > 1).collect();
+    duplicates.sort_by(|a, b| a[0].title.cmp(&b[0].title));
+    for group in duplicates {
+        let paths = group.iter().map(|w| w.path.as_str()).collect::<Vec<_>>();
+        findings.push(Finding::warning(
+            format!("Title '{}' is used by {}", group[0].title, paths.join(", ")),
+            "Rename one of them; staging and unstaging by title is ambiguous",
+        ));
+    }
+    for writing in &writings {
+        if let Some(wanted) = &writing.slug_collision {
+            findings.push(Finding::warning(
+                format!(
+                    "Slug '{}' of {} is taken, it is staged as '{}'",
+                    wanted, writing.path, writing.slug
+                ),
+                "Set a unique 'slug' in its frontmatter",
+            ));
+        }
+    }
+}
+
+/// Print the findings for one project; returns the number of errors
+pub fn print_report(name: &str, findings: &[Finding]) -> usize {
+    cli::section(&format!("Project '{}'", name));
+    if findings.is_empty() {
+        cli::success("No problems found");
+        cli::blank_line();
+        return 0;
+    }
+    for finding in findings {
+        let label = match finding.severity {
+            Severity::Error => "error".red().bold(),
+            Severity::Warning => "warning".yellow().bold(),
+        };
+        cli::list_item(&format!("{} {}", label, finding.message));
+        cli::list_detail("fix:", &finding.fix);
+    }
+    cli::blank_line();
+    findings.iter().filter(|f| f.severity == Severity::Error).count()
+}
@@ -0,0 +1,833 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+/// Syntax of a note's frontmatter block
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontmatterFormat {
+    /// `---` delimited YAML
+    Yaml,
+    /// `+++` delimited TOML, as used by Hugo and Zola
+    Toml,
+    /// A JSON object at the start of the file
+    Json,
+}
+
+/// Why a note's frontmatter could not be read
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    /// 1-based line in the note, when the parser reports one
+    pub line: Option<usize>,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, line: Option<usize>) -> Self {
+        ParseError {
+            message: message.into(),
+            line,
+        }
+    }
+
+    /// Wrap an error from parsing the text between the delimiters, moving the
+    /// parser's `line N` references to line numbers in the note
+    fn in_block(kind: &str, error: impl fmt::Display, line: Option<usize>) -> Self {
+        static LINE_PATTERN: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"\bline (\d+)").expect("Failed to create line regex"));
+        let error = error.to_string().trim().lines().collect::<Vec<_>>().join("; ");
+        let message = LINE_PATTERN.replace_all(&error, |caps: &Captures| {
+            let line: usize = caps[1].parse().unwrap_or(0);
+            format!("line {}", line + 1)
+        });
+        ParseError::new(
+            format!("Invalid {} frontmatter: {}", kind, message),
+            line.map(|l| l + 1),
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> Self {
+        error.message
+    }
+}
+
+/// A note split into frontmatter and body that remembers the source text, so
+/// edits only touch the keys that actually changed.
+pub struct Document {
+    pub format: FrontmatterFormat,
+    /// Frontmatter as written in the source, `Null` when the note has none
+    pub frontmatter: Value,
+    pub body: String,
+    /// Everything before the body, delimiters included
+    header: String,
+    /// Frontmatter text between the delimiters
+    raw: String,
+    /// Top-level TOML keys whose value is a date rather than a string
+    toml_dates: HashSet<String>,
+    newline: &'static str,
+}
+
+/// One top-level key of the frontmatter text, with the comments above it
+struct Segment {
+    key: Option<String>,
+    leading: String,
+    entry: String,
+    /// TOML `[table]` sections, which must stay after plain keys
+    is_table: bool,
+}
+
+impl Document {
+    pub fn parse(content: &str) -> Result<Self, ParseError> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        let newline = if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let first_line = content.lines().next().unwrap_or("").trim_end();
+
+        let format = match first_line {
+            "---" => FrontmatterFormat::Yaml,
+            "+++" => FrontmatterFormat::Toml,
+            _ if is_json_frontmatter(content) => FrontmatterFormat::Json,
+            _ => {
+                return Ok(Document {
+                    format: FrontmatterFormat::Yaml,
+                    frontmatter: Value::Null,
+                    body: content.to_string(),
+                    header: String::new(),
+                    raw: String::new(),
+                    toml_dates: HashSet::new(),
+                    newline,
+                })
+            }
+        };
+
+        let (header, raw, body) = match format {
+            FrontmatterFormat::Json => {
+                let mut stream =
+                    serde_json::Deserializer::from_str(content).into_iter::<serde_json::Value>();
+                match stream.next() {
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        return Err(ParseError::new(
+                            format!("Invalid JSON frontmatter: {}", e),
+                            Some(e.line()),
+                        ))
+                    }
+                    None => return Err(ParseError::new("Invalid JSON frontmatter", Some(1))),
+                }
+                let end = stream.byte_offset();
+                (
+                    content[..end].to_string(),
+                    content[..end].to_string(),
+                    content[end..].to_string(),
+                )
+            }
+            _ => {
+                let delimiter = first_line;
+                let mut offset = 0;
+                let mut raw_start = None;
+                let mut split = None;
+                for line in content.split_inclusive('\n') {
+                    let next = offset + line.len();
+                    match raw_start {
+                        None => raw_start = Some(next),
+                        Some(start) => {
+                            let trimmed = line.trim_end();
+                            let closes = trimmed == delimiter
+                                || (format == FrontmatterFormat::Yaml && trimmed == "...");
+                            if closes {
+                                split = Some((start, offset, next));
+                                break;
+                            }
+                        }
+                    }
+                    offset = next;
+                }
+                let (raw_start, raw_end, body_start) =
+                    split.ok_or_else(|| ParseError::new("Frontmatter block is not closed", Some(1)))?;
+                (
+                    content[..body_start].to_string(),
+                    content[raw_start..raw_end].to_string(),
+                    content[body_start..].to_string(),
+                )
+            }
+        };
+
+        let mut toml_dates = HashSet::new();
+        let frontmatter = match format {
+            FrontmatterFormat::Yaml if raw.trim().is_empty() => Value::Mapping(Mapping::new()),
+            FrontmatterFormat::Yaml => serde_yaml::from_str(&raw).map_err(|e| {
+                let line = e.location().map(|l| l.line());
+                ParseError::in_block("YAML", e, line)
+            })?,
+            FrontmatterFormat::Toml => {
+                let table: toml::Table = toml::from_str(&raw).map_err(|e| {
+                    let line = e.span().map(|span| raw[..span.start].matches('\n').count() + 1);
+                    ParseError::in_block("TOML", e.message(), line)
+                })?;
+                toml_dates.extend(
+                    table
+                        .iter()
+                        .filter(|(_, value)| value.is_datetime())
+                        .map(|(key, _)| key.clone()),
+                );
+                toml_to_yaml(toml::Value::Table(table))
+            }
+            FrontmatterFormat::Json => serde_json::from_str(&raw).map_err(|e| {
+                ParseError::new(format!("Invalid JSON frontmatter: {}", e), Some(e.line()))
+            })?,
+        };
+
+        Ok(Document {
+            format,
+            frontmatter,
+            body,
+            header,
+            raw,
+            toml_dates,
+            newline,
+        })
+    }
+
+    /// Write the note back with `frontmatter` and `body` in place of the originals.
+    ///
+    /// Keys whose value is unchanged keep their exact source text, changed
+    /// scalars are edited on their own line, and `renames` (old key -> new key)
+    /// rename keys in place. Only new or restructured values are re-serialized.
+    pub fn render(
+        &self,
+        frontmatter: &Value,
+        body: &str,
+        renames: &BTreeMap<String, String>,
+    ) -> String {
+        if frontmatter == &self.frontmatter {
+            return format!("{}{}", self.header, body);
+        }
+        let new = match frontmatter {
+            Value::Mapping(mapping) => mapping.clone(),
+            _ => Mapping::new(),
+        };
+
+        if self.header.is_empty() {
+            return fresh_block(FrontmatterFormat::Yaml, &new, self.newline) + body;
+        }
+
+        match self.format {
+            FrontmatterFormat::Json => {
+                let json = serde_json::to_string_pretty(&Value::Mapping(new))
+                    .unwrap_or_else(|_| "{}".to_string());
+                format!("{}{}", json.replace('\n', self.newline), body)
+            }
+            _ => {
+                let delimiter = if self.format == FrontmatterFormat::Toml {
+                    "+++"
+                } else {
+                    "---"
+                };
+                let open = self.header.lines().next().unwrap_or(delimiter);
+                let close = self.header[self.header.trim_end().rfind('\n').map_or(0, |i| i + 1)..]
+                    .to_string();
+                format!(
+                    "{}{}{}{}{}",
+                    open,
+                    self.newline,
+                    self.render_entries(&new, renames),
+                    close,
+                    body
+                )
+            }
+        }
+    }
+
+    /// Like `render`, but with the frontmatter written as `format`. Notes in
+    /// another format are converted as a whole, so their source text is not kept.
+    pub fn render_as(
+        &self,
+        format: FrontmatterFormat,
+        frontmatter: &Value,
+        body: &str,
+        renames: &BTreeMap<String, String>,
+    ) -> String {
+        let keeps_text = !self.header.is_empty() || format == FrontmatterFormat::Yaml;
+        if format == self.format && keeps_text {
+            return self.render(frontmatter, body, renames);
+        }
+        let mapping = frontmatter.as_mapping().cloned().unwrap_or_default();
+        fresh_block(format, &mapping, self.newline) + body
+    }
+
+    fn render_entries(&self, new: &Mapping, renames: &BTreeMap<String, String>) -> String {
+        let toml = self.format == FrontmatterFormat::Toml;
+        let segments = if toml {
+            split_toml(&self.raw)
+        } else {
+            split_yaml(&self.raw)
+        };
+        let original = self.frontmatter.as_mapping().cloned().unwrap_or_default();
+
+        let mut plain = String::new();
+        let mut tables = String::new();
+        let mut trailer = String::new();
+        let mut emitted: HashSet<String> = HashSet::new();
+
+        for segment in &segments {
+            let Some(key) = &segment.key else {
+                // Trailing comments stay at the end
+                trailer.push_str(&segment.leading);
+                continue;
+            };
+            let target = if new.contains_key(key.as_str()) {
+                key.clone()
+            } else {
+                match renames.get(key) {
+                    Some(renamed)
+                        if new.contains_key(renamed.as_str())
+                            && !original.contains_key(renamed.as_str()) =>
+                    {
+                        renamed.clone()
+                    }
+                    _ => continue,
+                }
+            };
+            let value = &new[target.as_str()];
+            let out = if segment.is_table {
+                &mut tables
+            } else {
+                &mut plain
+            };
+
+            if original.get(key.as_str()) == Some(value) {
+                out.push_str(&segment.leading);
+                out.push_str(&rename_entry(&segment.entry, key, &target, toml));
+            } else if emitted.insert(target.clone()) {
+                out.push_str(&segment.leading);
+                match self.edit_scalar_line(&segment.entry, &target, value) {
+                    Some(line) => out.push_str(&line),
+                    None => {
+                        let (mut text, is_table) =
+                            self.serialize_entry(&target, value);
+                        // Keep the blank lines that separated the old entry from the next one
+                        let content_end = segment.entry.trim_end().len();
+                        let spacing = &segment.entry[content_end..];
+                        text.extend(spacing.split_inclusive('\n').skip(1));
+                        if is_table {
+                            tables.push_str(&text)
+                        } else {
+                            plain.push_str(&text)
+                        }
+                    }
+                }
+            }
+            emitted.insert(target);
+        }
+
+        // Keys the transforms added go after the existing ones
+        for (key, value) in new {
+            let Some(key) = key.as_str() else { continue };
+            if emitted.insert(key.to_string()) {
+                let (text, is_table) = self.serialize_entry(key, value);
+                if is_table {
+                    tables.push_str(&text);
+                } else {
+                    // Before any blank lines that set the plain keys apart from the tables
+                    let content_end = plain.trim_end().len();
+                    let line_end = plain[content_end..]
+                        .find('\n')
+                        .map_or(plain.len(), |i| content_end + i + 1);
+                    plain.insert_str(line_end, &text);
+                }
+            }
+        }
+        plain + &tables + &trailer
+    }
+
+    /// TOML value for a top-level key. Strings stay strings unless the key held
+    /// a date in the source, so a title like "2024-01-01" is not turned into one.
+    fn toml_value(&self, key: &str, value: &Value) -> Option<toml::Value> {
+        match value {
+            Value::String(s) if self.toml_dates.contains(key) => Some(
+                s.parse::<toml::value::Datetime>()
+                    .map_or_else(|_| toml::Value::String(s.clone()), toml::Value::Datetime),
+            ),
+            _ => yaml_to_toml(value),
+        }
+    }
+
+    /// Replace the value of a single-line scalar entry, keeping the spacing and any
+    /// trailing comment. Returns `None` when the entry needs a full rewrite.
+    fn edit_scalar_line(&self, entry: &str, key: &str, value: &Value) -> Option<String> {
+        let toml = self.format == FrontmatterFormat::Toml;
+        let mut lines = entry.split_inclusive('\n');
+        let first = lines.next()?;
+        let trailing: String = lines.collect();
+        if !trailing.trim().is_empty() || (toml && first.starts_with('[')) {
+            return None;
+        }
+        let line = first.trim_end_matches(['\r', '\n']);
+        let newline = &first[line.len()..];
+        let separator = line.find(if toml { '=' } else { ':' })?;
+        let key_end = line[..separator].trim_end().len();
+        let old_key = line[..key_end].trim_matches(['"', '\'']);
+        if toml && old_key.contains('.') {
+            return None;
+        }
+
+        let value_text = if toml {
+            // A renamed key stays a date when the source held one
+            match self.toml_value(old_key, value)? {
+                toml::Value::Table(_) | toml::Value::Array(_) => return None,
+                // Displayed on its own, a datetime comes out as serde's private wrapper table
+                toml::Value::Datetime(datetime) => datetime.to_string(),
+                scalar => scalar.to_string(),
+            }
+        } else {
+            if matches!(
+                value,
+                Value::Mapping(_) | Value::Sequence(_) | Value::Tagged(_)
+            ) {
+                return None;
+            }
+            serde_yaml::to_string(value).ok()?.trim_end().to_string()
+        };
+        if value_text.contains('\n') {
+            return None;
+        }
+        let rest = &line[separator + 1..];
+
+        // Keep a trailing comment unless the old value is quoted and may contain '#'
+        let value_end = match rest.find(" #") {
+            Some(pos) if !rest[..pos].contains(['"', '\'']) => pos,
+            _ => rest.len(),
+        };
+        let old_value = &rest[..value_end];
+        let lead = old_value.len() - old_value.trim_start().len();
+        let indent = if lead == 0 { " " } else { &old_value[..lead] };
+        let comment = if value_end < rest.len() {
+            &rest[lead + old_value.trim().len()..]
+        } else {
+            ""
+        };
+        let key_text = if old_key == key {
+            line[..key_end].to_string()
+        } else if toml {
+            toml_key(key)
+        } else {
+            yaml_key(key)
+        };
+        Some(
+            format!(
+                "{}{}{}{}{}{}{}",
+                key_text,
+                &line[key_end..separator],
+                &line[separator..separator + 1],
+                indent,
+                value_text,
+                comment,
+                newline
+            ) + &trailing,
+        )
+    }
+
+    /// Serialize a single key/value, returning whether it is a TOML table section
+    fn serialize_entry(&self, key: &str, value: &Value) -> (String, bool) {
+        let newline = self.newline;
+        if self.format == FrontmatterFormat::Toml {
+            let Some(toml_value) = self.toml_value(key, value) else {
+                // TOML has no null
+                return (String::new(), false);
+            };
+            let mut table = toml::Table::new();
+            table.insert(key.to_string(), toml_value);
+            let text = toml::to_string(&table).unwrap_or_default();
+            let is_table = text.starts_with('[');
+            (text.replace('\n', newline), is_table)
+        } else {
+            let mut mapping = Mapping::new();
+            mapping.insert(Value::String(key.to_string()), value.clone());
+            let text = serde_yaml::to_string(&Value::Mapping(mapping)).unwrap_or_default();
+            (text.replace('\n', newline), false)
+        }
+    }
+}
+
+/// JSON frontmatter opens with `{` on a line of its own, or is a single JSON
+/// object followed by a blank line. Bodies that merely start with a brace, such
+/// as Liquid `{% %}` tags, MDX `{/* */}` comments or Hugo `{{< >}}` shortcodes,
+/// are not frontmatter.
+fn is_json_frontmatter(content: &str) -> bool {
+    if content.lines().next().is_some_and(|line| line.trim_end() == "{") {
+        return true;
+    }
+    if !content.starts_with('{') {
+        return false;
+    }
+    let mut stream = serde_json::Deserializer::from_str(content).into_iter::<serde_json::Value>();
+    if !matches!(stream.next(), Some(Ok(serde_json::Value::Object(_)))) {
+        return false;
+    }
+    let mut rest = content[stream.byte_offset()..].split('\n');
+    let closing_line = rest.next().unwrap_or("");
+    closing_line.trim().is_empty() && rest.next().is_none_or(|line| line.trim().is_empty())
+}
+
+/// A complete frontmatter block in `format`, or nothing for empty frontmatter
+fn fresh_block(format: FrontmatterFormat, frontmatter: &Mapping, newline: &str) -> String {
+    if frontmatter.is_empty() {
+        return String::new();
+    }
+    let value = Value::Mapping(frontmatter.clone());
+    let block = match format {
+        FrontmatterFormat::Yaml => format!("---\n{}---\n", serde_yaml::to_string(&value).unwrap_or_default()),
+        FrontmatterFormat::Toml => {
+            let toml = yaml_to_toml(&value).and_then(|v| toml::to_string(&v).ok()).unwrap_or_default();
+            format!("+++\n{}+++\n", toml)
+        }
+        FrontmatterFormat::Json => {
+            format!("{}\n", serde_json::to_string_pretty(&value).unwrap_or_else(|_| "{}".to_string()))
+        }
+    };
+    block.replace('\n', newline)
+}
+
+/// Split YAML frontmatter into top-level keys. Comment lines belong to the key below them.
+fn split_yaml(raw: &str) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut pending = String::new();
+    for line in raw.split_inclusive('\n') {
+        let starts_key = !line.starts_with([' ', '\t', '#', '-', '\r', '\n'])
+            && line.contains(':')
+            && !line.trim().is_empty();
+        if starts_key {
+            let key = line[..line.find(':').unwrap_or(0)]
+                .trim()
+                .trim_matches(['"', '\''])
+                .to_string();
+            segments.push(Segment {
+                key: Some(key),
+                leading: std::mem::take(&mut pending),
+                entry: line.to_string(),
+                is_table: false,
+            });
+        } else if line.starts_with('#') || (!pending.is_empty() && line.trim().is_empty()) {
+            pending.push_str(line);
+        } else {
+            match segments.last_mut() {
+                Some(segment) => segment.entry.push_str(line),
+                None => pending.push_str(line),
+            }
+        }
+    }
+    push_trailer(&mut segments, pending);
+    segments
+}
+
+/// Split TOML frontmatter into top-level keys and `[table]` sections
+fn split_toml(raw: &str) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut pending = String::new();
+    let mut in_table = false;
+    for line in raw.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let header = line.starts_with('[');
+        let assignment =
+            !in_table && !line.starts_with([' ', '\t', '#']) && toml_assignment_key(line).is_some();
+        if header || assignment {
+            let key = if header {
+                in_table = true;
+                let path = trimmed
+                    .trim_start_matches('[')
+                    .split(']')
+                    .next()
+                    .unwrap_or("");
+                first_toml_key(path)
+            } else {
+                toml_assignment_key(line).unwrap_or_default()
+            };
+            segments.push(Segment {
+                key: Some(key),
+                leading: std::mem::take(&mut pending),
+                entry: line.to_string(),
+                is_table: header,
+            });
+        } else if line.starts_with('#') || (!pending.is_empty() && trimmed.is_empty()) {
+            pending.push_str(line);
+        } else {
+            match segments.last_mut() {
+                Some(segment) => segment.entry.push_str(line),
+                None => pending.push_str(line),
+            }
+        }
+    }
+    push_trailer(&mut segments, pending);
+    segments
+}
+
+fn push_trailer(segments: &mut Vec<Segment>, pending: String) {
+    if !pending.is_empty() {
+        segments.push(Segment {
+            key: None,
+            leading: pending,
+            entry: String::new(),
+            is_table: false,
+        });
+    }
+}
+
+/// The top-level key assigned on a `key = value` line
+fn toml_assignment_key(line: &str) -> Option<String> {
+    let eq = line.find('=')?;
+    let path = line[..eq].trim();
+    if path.is_empty() {
+        return None;
+    }
+    Some(first_toml_key(path))
+}
+
+/// First component of a dotted TOML key, unquoted
+fn first_toml_key(path: &str) -> String {
+    let path = path.trim();
+    if let Some(quote) = path.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let rest = &path[1..];
+        return rest[..rest.find(quote).unwrap_or(rest.len())].to_string();
+    }
+    path.split('.').next().unwrap_or("").trim().to_string()
+}
+
+/// Swap the key name on the first line of an entry, leaving the value text alone
+fn rename_entry(entry: &str, old: &str, new: &str, toml: bool) -> String {
+    if old == new {
+        return entry.to_string();
+    }
+    let (first, rest) = entry.split_at(entry.find('\n').map_or(entry.len(), |i| i + 1));
+    let renamed = if toml && first.starts_with('[') {
+        let brackets = first.len() - first.trim_start_matches('[').len();
+        let inner = &first[brackets..];
+        let old_len = inner.find(['.', ']']).unwrap_or(inner.len());
+        format!(
+            "{}{}{}",
+            &first[..brackets],
+            toml_key(new),
+            &inner[old_len..]
+        )
+    } else {
+        let separator = if toml { '=' } else { ':' };
+        let split = first.find(separator).unwrap_or(0);
+        let key_end = first[..split].trim_end().len();
+        let old_path = &first[..key_end];
+        let suffix = if toml {
+            old_path
+                .strip_prefix(old)
+                .filter(|s| s.starts_with('.'))
+                .unwrap_or("")
+        } else {
+            ""
+        };
+        let key = if toml { toml_key(new) } else { yaml_key(new) };
+        format!("{}{}{}", key, suffix, &first[key_end..])
+    };
+    renamed + rest
+}
+
+fn yaml_key(key: &str) -> String {
+    serde_yaml::to_string(key)
+        .map(|k| k.trim_end().to_string())
+        .unwrap_or_else(|_| key.to_string())
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        toml::Value::String(key.to_string()).to_string()
+    }
+}
+
+/// TOML dates become strings so transforms see the same values as in YAML notes
+fn toml_to_yaml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Number(i.into()),
+        toml::Value::Float(f) => Value::Number(f.into()),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => Value::Sequence(items.into_iter().map(toml_to_yaml).collect()),
+        toml::Value::Table(table) => Value::Mapping(
+            table
+                .into_iter()
+                .map(|(k, v)| (Value::String(k), toml_to_yaml(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Convert to TOML. YAML has no date type, so strings are written as strings.
+fn yaml_to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64()?),
+        },
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Sequence(items) => {
+            toml::Value::Array(items.iter().filter_map(yaml_to_toml).collect())
+        }
+        Value::Mapping(mapping) => toml::Value::Table(
+            mapping
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_string(), yaml_to_toml(v)?)))
+                .collect(),
+        ),
+        Value::Tagged(tagged) => return yaml_to_toml(&tagged.value),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(content: &str, key: &str, value: Value) -> String {
+        let document = Document::parse(content).unwrap();
+        let mut frontmatter = document.frontmatter.clone();
+        frontmatter[key] = value;
+        document.render(&frontmatter, &document.body, &BTreeMap::new())
+    }
+
+    #[test]
+    fn unchanged_frontmatter_round_trips() {
+        for content in [
+            "---\ntitle: Hello # greeting\ntags:\n  - a\n---\nBody\n",
+            "+++\ntitle = \"Hello\"\ndate = 2024-01-01\n\n[extra]\nkey = 1\n+++\nBody\n",
+            "{\n  \"title\": \"Hello\"\n}\n\nBody\n",
+        ] {
+            let document = Document::parse(content).unwrap();
+            assert_eq!(document.frontmatter["title"], Value::from("Hello"));
+            assert_eq!(document.render(&document.frontmatter, &document.body, &BTreeMap::new()), content);
+        }
+    }
+
+    #[test]
+    fn edits_only_the_changed_line() {
+        assert_eq!(
+            edit("---\ntitle: Hello # greeting\ndraft: true\n---\nBody", "draft", Value::Bool(false)),
+            "---\ntitle: Hello # greeting\ndraft: false\n---\nBody"
+        );
+        assert_eq!(
+            edit("+++\ndraft = true  # not yet\n\n[extra]\nkey = 1\n+++\n", "draft", Value::Bool(false)),
+            "+++\ndraft = false  # not yet\n\n[extra]\nkey = 1\n+++\n"
+        );
+        assert_eq!(
+            edit("---\ntitle: Hello\n---\n", "slug", Value::from("hello")),
+            "---\ntitle: Hello\nslug: hello\n---\n"
+        );
+    }
+
+    #[test]
+    fn edit_scalar_line_keeps_spacing_and_comments() {
+        let document = Document::parse("---\ntitle:   Old # note\n---\n").unwrap();
+        assert_eq!(
+            document.edit_scalar_line("title:   Old # note\n", "title", &Value::from("New")),
+            Some("title:   New # note\n".to_string())
+        );
+        assert_eq!(document.edit_scalar_line("tags:\n  - a\n", "tags", &Value::from("a")), None);
+        assert_eq!(
+            document.edit_scalar_line("title: Old\n", "title", &Value::Sequence(vec![Value::from("a")])),
+            None
+        );
+    }
+
+    #[test]
+    fn handles_crlf_and_bom() {
+        let content = "\u{feff}---\r\ntitle: Hello\r\ndraft: true\r\n---\r\nBody\r\n";
+        let document = Document::parse(content).unwrap();
+        assert_eq!(document.frontmatter["title"], Value::from("Hello"));
+        assert_eq!(document.body, "Body\r\n");
+        assert_eq!(
+            edit(content, "draft", Value::Bool(false)),
+            "---\r\ntitle: Hello\r\ndraft: false\r\n---\r\nBody\r\n"
+        );
+        assert_eq!(
+            edit(content, "slug", Value::from("hello")),
+            "---\r\ntitle: Hello\r\ndraft: true\r\nslug: hello\r\n---\r\nBody\r\n"
+        );
+    }
+
+    #[test]
+    fn toml_dates_stay_dates_and_strings_stay_strings() {
+        let content = "+++\ntitle = \"Hello\"\ndate = 2024-01-01\n+++\n";
+        assert_eq!(
+            edit(content, "date", Value::from("2024-02-02")),
+            "+++\ntitle = \"Hello\"\ndate = 2024-02-02\n+++\n"
+        );
+        assert_eq!(
+            edit(content, "title", Value::from("2024-03-03")),
+            "+++\ntitle = \"2024-03-03\"\ndate = 2024-01-01\n+++\n"
+        );
+        assert_eq!(
+            yaml_to_toml(&Value::from("2024-01-01")),
+            Some(toml::Value::String("2024-01-01".to_string()))
+        );
+    }
+
+    #[test]
+    fn converts_between_formats() {
+        let document = Document::parse("---\ntitle: Hello\ndate: 2024-01-01\n---\nBody").unwrap();
+        let rendered = document.render_as(
+            FrontmatterFormat::Toml,
+            &document.frontmatter,
+            &document.body,
+            &BTreeMap::new(),
+        );
+        assert_eq!(rendered, "+++\ntitle = \"Hello\"\ndate = \"2024-01-01\"\n+++\nBody");
+        let toml = Document::parse(&rendered).unwrap();
+        assert_eq!(toml.frontmatter, document.frontmatter);
+    }
+
+    #[test]
+    fn json_frontmatter_needs_its_own_line_or_a_blank_line() {
+        let inline = Document::parse("{\"title\": \"Hello\"}\n\nBody").unwrap();
+        assert_eq!(inline.format, FrontmatterFormat::Json);
+        assert_eq!(inline.frontmatter["title"], Value::from("Hello"));
+
+        for body in [
+            "{% include note.html %}\nText",
+            "{/* MDX comment */}\nText",
+            "{{< figure src=\"a.png\" >}}\nText",
+            "{\"not\": \"frontmatter\"} followed by text",
+        ] {
+            let document = Document::parse(body).unwrap();
+            assert!(document.frontmatter.is_null(), "{}", body);
+            assert_eq!(document.body, body);
+        }
+    }
+
+    #[test]
+    fn reports_lines_in_the_note() {
+        let error = Document::parse("---\ntitle: Hello\ntags: [a\n---\n").err().unwrap();
+        assert!(error.message.starts_with("Invalid YAML frontmatter"));
+        assert!(error.line.is_some_and(|line| line >= 3));
+        assert!(Document::parse("---\ntitle: Hello\n").is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::{Captures, Regex};
+use walkdir::WalkDir;
+
+use crate::imaging::ProcessedImage;
+
+/// Characters escaped in asset URLs; keeps `/` so prefixes stay readable
+const PATH_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'(')
+    .add(b')')
+    .add(b'[')
+    .add(b']');
+
+/// A parsed `![[file|alt|WIDTHxHEIGHT]]` embed
+#[derive(Debug, PartialEq)]
+pub struct Embed<'a> {
+    pub file: &'a str,
+    pub alt: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl<'a> Embed<'a> {
+    /// Parse the inside of `![[...]]`.
+    ///
+    /// A trailing `300` or `300x200` segment is a size, anything else after
+    /// the file name is alt text.
+    pub fn parse(inner: &'a str) -> Self {
+        let mut segments: Vec<&str> = inner.split('|').collect();
+        let file = segments.remove(0).trim();
+
+        let (mut width, mut height) = (None, None);
+        if let Some(size) = segments.last().and_then(|s| parse_size(s.trim())) {
+            (width, height) = size;
+            segments.pop();
+        }
+
+        let alt = Some(segments.join("|").trim().to_string()).filter(|a| !a.is_empty());
+        Embed {
+            file,
+            alt,
+            width,
+            height,
+        }
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.file.rsplit('/').next().unwrap_or(self.file)
+    }
+
+    /// Embeds of other notes (`![[Note]]`, `![[Note#Section]]`) rather than files
+    pub fn is_note(&self) -> bool {
+        let name = self.file_name();
+        name.contains('#') || Path::new(name).extension().is_none_or(|ext| ext == "md")
+    }
+
+    /// Alt text, falling back to the file name without its extension
+    fn alt_text(&self) -> String {
+        self.alt.clone().unwrap_or_else(|| {
+            Path::new(self.file_name())
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+    }
+}
+
+fn parse_size(segment: &str) -> Option<(Option<u32>, Option<u32>)> {
+    match segment.split_once('x') {
+        Some((w, h)) => Some((Some(w.parse().ok()?), Some(h.parse().ok()?))),
+        None => Some((Some(segment.parse().ok()?), None)),
+    }
+}
+
+pub struct EmbedOptions {
+    /// URL prefix of the target asset directory, `None` when assets sit next
+    /// to the post (bundle layout) and are linked relatively
+    pub target_prefix: Option<String>,
+    pub use_mdx: bool,
+    /// File names under `source_asset_dir`, `None` when it is not configured
+    pub known_assets: Option<HashSet<String>>,
+}
+
+impl EmbedOptions {
+    pub fn new(target_prefix: Option<String>, use_mdx: bool, source_asset_dir: Option<String>) -> Self {
+        let known_assets = source_asset_dir.filter(|dir| !dir.is_empty()).map(|dir| {
+            WalkDir::new(dir)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect()
+        });
+        Self {
+            target_prefix,
+            use_mdx,
+            known_assets,
+        }
+    }
+
+    fn url_for(&self, file_name: &str) -> String {
+        let file_name = utf8_percent_encode(file_name, PATH_ENCODE_SET).to_string();
+        match &self.target_prefix {
+            Some(prefix) => format!("{}/{}", prefix.trim_end_matches('/'), file_name),
+            None => file_name,
+        }
+    }
+
+    fn render(&self, embed: &Embed, processed: Option<&ProcessedImage>) -> String {
+        let file_name = processed.map_or(embed.file_name(), |p| p.file_name.as_str());
+        let url = self.url_for(file_name);
+        let alt = embed.alt_text();
+        let srcset = processed.filter(|p| !p.variants.is_empty()).map(|p| {
+            let mut sources: Vec<String> = p
+                .variants
+                .iter()
+                .map(|(width, name)| format!("{} {}w", self.url_for(name), width))
+                .collect();
+            sources.push(format!("{} {}w", url, p.width));
+            sources.join(", ")
+        });
+        if embed.width.is_none() && embed.height.is_none() && srcset.is_none() {
+            return format!("![{}]({})", alt.replace(']', "\\]"), url);
+        }
+
+        let alt = alt.replace('"', "&quot;");
+        let mut attrs = vec![format!("src=\"{}\"", url), format!("alt=\"{}\"", alt)];
+        if let Some(srcset) = &srcset {
+            // JSX spells the attribute srcSet
+            let name = if self.use_mdx { "srcSet" } else { "srcset" };
+            attrs.push(format!("{}=\"{}\"", name, srcset));
+        }
+        if self.use_mdx {
+            attrs.extend(embed.width.map(|w| format!("width={{{}}}", w)));
+            attrs.extend(embed.height.map(|h| format!("height={{{}}}", h)));
+        } else {
+            attrs.extend(embed.width.map(|w| format!("width=\"{}\"", w)));
+            attrs.extend(embed.height.map(|h| format!("height=\"{}\"", h)));
+        }
+        // A plain <img> is valid MDX too and, unlike a component, needs no import
+        format!("<img {} />", attrs.join(" "))
+    }
+}
+
+/// Convert Obsidian file embeds to markdown images or `<img>` tags. Note
+/// embeds are left as they are. Files missing from the source asset directory
+/// are reported through `warn`. Images in `processed` point at their converted
+/// file and list their responsive variants.
+pub fn convert_embeds(
+    content: &str,
+    options: &EmbedOptions,
+    processed: &HashMap<String, ProcessedImage>,
+    mut warn: impl FnMut(String),
+) -> String {
+    let pattern = Regex::new(r"!\[\[([^\[\]]+)\]\]").expect("Failed to create image wikilink regex");
+    pattern
+        .replace_all(content, |caps: &Captures| {
+            let embed = Embed::parse(&caps[1]);
+            if embed.is_note() {
+                return caps[0].to_string();
+            }
+            if let Some(known) = &options.known_assets {
+                if !known.contains(embed.file_name()) {
+                    warn(format!("Embedded file '{}' not found in source_asset_dir", embed.file));
+                }
+            }
+            options.render(&embed, processed.get(embed.file_name()))
+        })
+        .to_string()
+}
+
+/// Point standard markdown images (`![alt](images/cat.png)`) at the converted
+/// file of images in `processed`. Only the file name in the destination
+/// changes, so the path and any title are kept as written.
+pub fn rewrite_image_destinations(content: &str, processed: &HashMap<String, ProcessedImage>) -> String {
+    let mut edits = Vec::new();
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        let Event::Start(Tag::Image(_, dest, _)) = event else {
+            continue;
+        };
+        if dest.contains("://") {
+            continue;
+        }
+        let path = dest.split(['?', '#']).next().unwrap_or(&dest);
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let Some(image) = processed.get(percent_decode_str(name).decode_utf8_lossy().as_ref()) else {
+            continue;
+        };
+        // Reference-style images have their destination elsewhere and are skipped
+        let Some(dest_start) = content[range.clone()].rfind(dest.as_ref()) else {
+            continue;
+        };
+        let start = range.start + dest_start + path.len() - name.len();
+        let file_name = utf8_percent_encode(&image.file_name, PATH_ENCODE_SET).to_string();
+        edits.push((start..start + name.len(), file_name));
+    }
+
+    let mut output = content.to_string();
+    for (range, file_name) in edits.into_iter().rev() {
+        output.replace_range(range, &file_name);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(prefix: Option<&str>, use_mdx: bool) -> EmbedOptions {
+        EmbedOptions {
+            target_prefix: prefix.map(str::to_string),
+            use_mdx,
+            known_assets: None,
+        }
+    }
+
+    fn convert(content: &str, options: &EmbedOptions) -> String {
+        convert_embeds(content, options, &HashMap::new(), |_| {})
+    }
+
+    #[test]
+    fn parses_alt_and_size() {
+        let embed = Embed::parse("img.png|A cat|300x200");
+        assert_eq!(embed.file, "img.png");
+        assert_eq!(embed.alt.as_deref(), Some("A cat"));
+        assert_eq!((embed.width, embed.height), (Some(300), Some(200)));
+
+        let embed = Embed::parse("img.png|300");
+        assert_eq!((embed.alt, embed.width, embed.height), (None, Some(300), None));
+    }
+
+    #[test]
+    fn plain_embeds_become_markdown_images() {
+        let options = options(Some("/images/"), false);
+        assert_eq!(convert("![[My Cat.png]]", &options), "![My Cat](/images/My%20Cat.png)");
+        assert_eq!(convert("![[Other Note]]", &options), "![[Other Note]]");
+    }
+
+    #[test]
+    fn empty_prefix_links_from_the_site_root() {
+        assert_eq!(convert("![[cat.png]]", &options(Some(""), false)), "![cat](/cat.png)");
+    }
+
+    #[test]
+    fn bundles_link_relative_to_the_post() {
+        assert_eq!(convert("![[cat.png]]", &options(None, false)), "![cat](cat.png)");
+    }
+
+    #[test]
+    fn sized_embeds_become_img_tags() {
+        assert_eq!(
+            convert("![[cat.png|A \"cat\"|300]]", &options(Some("/img"), false)),
+            "<img src=\"/img/cat.png\" alt=\"A &quot;cat&quot;\" width=\"300\" />"
+        );
+        assert_eq!(
+            convert("![[cat.png|300x200]]", &options(Some("/img"), true)),
+            "<img src=\"/img/cat.png\" alt=\"cat\" width={300} height={200} />"
+        );
+    }
+
+    #[test]
+    fn markdown_images_point_at_converted_files() {
+        let processed = HashMap::from([
+            ("My Cat.png".to_string(), ProcessedImage::converted("My Cat.webp", 800)),
+            ("dog.jpg".to_string(), ProcessedImage::converted("dog.webp", 800)),
+        ]);
+        let body = "![A cat](images/My%20Cat.png \"Title\")\n\n\
+                    ![Dog](<dog.jpg>) ![Bird](bird.png) ![Remote](https://example.com/dog.jpg)\n\n\
+                    `![code](dog.jpg)`";
+        assert_eq!(
+            rewrite_image_destinations(body, &processed),
+            "![A cat](images/My%20Cat.webp \"Title\")\n\n\
+             ![Dog](<dog.webp>) ![Bird](bird.png) ![Remote](https://example.com/dog.jpg)\n\n\
+             `![code](dog.jpg)`"
+        );
+    }
+
+    #[test]
+    fn warns_about_unknown_files() {
+        let mut options = options(Some("/img"), false);
+        options.known_assets = Some(HashSet::from(["cat.png".to_string()]));
+        let mut warnings = Vec::new();
+        convert_embeds("![[cat.png]] ![[dog.png]]", &options, &HashMap::new(), |w| warnings.push(w));
+        assert_eq!(warnings, vec!["Embedded file 'dog.png' not found in source_asset_dir"]);
+    }
+}
@@ -1,10 +1,82 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
-use regex::Regex;
-use serde_yaml::Value;
+use chrono::NaiveDate;
+use serde_yaml::{Mapping, Value};
 
 use crate::asset::Asset;
-use crate::config::{Config, HeroImage, Image};
+use crate::config::{Config, FrontmatterMap, HeroImage, Image};
+use crate::naming;
+use crate::writing::Writing;
+
+/// Rename top-level frontmatter keys in place, keeping their position.
+///
+/// Keys whose new name is already present are left untouched.
+pub fn rename_keys(frontmatter: &mut Value, renames: &BTreeMap<String, String>) {
+    let Some(mapping) = frontmatter.as_mapping_mut() else {
+        return;
+    };
+    let existing: Vec<String> = mapping
+        .keys()
+        .filter_map(|k| k.as_str().map(str::to_string))
+        .collect();
+    let renamed: Mapping = std::mem::take(mapping)
+        .into_iter()
+        .map(|(key, value)| {
+            let new_key = key
+                .as_str()
+                .and_then(|k| renames.get(k))
+                .filter(|new_name| !existing.contains(new_name));
+            match new_key {
+                Some(new_name) => (Value::String(new_name.clone()), value),
+                None => (key, value),
+            }
+        })
+        .collect();
+    *mapping = renamed;
+}
+
+/// Rename source-side aliases to the keys LazyDraft reads.
+///
+/// A string `draft` alias such as `status: draft` becomes a boolean, true
+/// only when the value is "draft".
+pub fn normalize_source_keys(frontmatter: &mut Value, key_map: &FrontmatterMap) {
+    let aliases: BTreeMap<String, String> = key_map
+        .source
+        .iter()
+        .map(|(canonical, alias)| (alias.clone(), canonical.clone()))
+        .collect();
+    rename_keys(frontmatter, &aliases);
+
+    if key_map.source.contains_key("draft") {
+        if let Some(status) = frontmatter["draft"].as_str() {
+            frontmatter["draft"] = Value::Bool(status.eq_ignore_ascii_case("draft"));
+        }
+    }
+}
+
+/// Rename keys to the names the target site expects, right before serialization
+pub fn apply_target_keys(frontmatter: &mut Value, key_map: &FrontmatterMap) {
+    rename_keys(frontmatter, &key_map.target);
+}
+
+/// Source key -> target key for every name the map changes between the two
+/// sides, so a rendered document can rename keys in place.
+pub fn key_renames(key_map: &FrontmatterMap) -> BTreeMap<String, String> {
+    let mut renames = BTreeMap::new();
+    for canonical in key_map.source.keys().chain(key_map.target.keys()) {
+        let target = key_map.target.get(canonical).unwrap_or(canonical);
+        if let Some(alias) = key_map.source.get(canonical) {
+            if alias != target {
+                renames.insert(alias.clone(), target.clone());
+            }
+        }
+        if canonical != target {
+            renames.insert(canonical.clone(), target.clone());
+        }
+    }
+    renames
+}
 
 /// Strip a prefix from all tags in frontmatter
 pub fn strip_tags(frontmatter: &mut Value, tag_prefix: &str) {
@@ -39,14 +111,14 @@ pub fn remove_empty_values(value: &mut Value) {
             }
         }
         Value::Sequence(seq) => {
-            seq.iter_mut().for_each(|v| remove_empty_values(v));
+            seq.iter_mut().for_each(remove_empty_values);
         }
         _ => {}
     }
 }
 
 /// Add cover image to frontmatter from matching asset
-pub fn add_cover_image(frontmatter: &mut Value, config: &Config, asset_list: &[Asset]) {
+pub fn add_cover_image(frontmatter: &mut Value, target_prefix: &str, asset_list: &[Asset]) {
     let asset_prefix = frontmatter["assetPrefix"].as_str().unwrap_or("");
     if asset_prefix.is_empty() {
         return;
@@ -57,10 +129,6 @@ pub fn add_cover_image(frontmatter: &mut Value, config: &Config, asset_list: &[A
         .filter(|asset| asset.asset_path.contains(&property_to_check))
         .collect();
     if !matching_assets.is_empty() {
-        let target_prefix = config
-            .target_asset_prefix
-            .as_ref()
-            .expect("target asset prefix should be set");
         let header_name = Path::new(
             matching_assets
                 .first()
@@ -83,7 +151,7 @@ pub fn add_cover_image(frontmatter: &mut Value, config: &Config, asset_list: &[A
 }
 
 /// Add hero image to frontmatter from matching asset
-pub fn add_hero_image(frontmatter: &mut Value, config: &Config, asset_list: &[Asset]) {
+pub fn add_hero_image(frontmatter: &mut Value, target_prefix: &str, asset_list: &[Asset]) {
     let asset_prefix = frontmatter["assetPrefix"].as_str().unwrap_or("");
     if asset_prefix.is_empty() {
         return;
@@ -94,10 +162,6 @@ pub fn add_hero_image(frontmatter: &mut Value, config: &Config, asset_list: &[As
         .filter(|asset| asset.asset_path.contains(&property_to_check))
         .collect();
     if !matching_assets.is_empty() {
-        let target_prefix = config
-            .target_hero_image_prefix
-            .as_ref()
-            .expect("target hero image prefix should be set");
         let header_name = Path::new(
             matching_assets
                 .first()
@@ -119,49 +183,25 @@ pub fn add_hero_image(frontmatter: &mut Value, config: &Config, asset_list: &[As
     }
 }
 
-/// Convert [[wikilink]] image references to standard markdown image syntax
-pub fn change_image_formats(content: String, config: &Config) -> String {
-    let pattern = Regex::new(r"!\[\[(.*?)\]\]").expect("Failed to create image wikilink regex");
-    let target_prefix = &config.target_asset_prefix;
-    pattern
-        .replace_all(&content, |caps: &regex::Captures| {
-            if let Some(link) = caps.get(1) {
-                format!(
-                    "![]({}/{})",
-                    target_prefix.as_deref().unwrap_or(""),
-                    link.as_str()
-                )
-            } else {
-                caps.get(0).unwrap().as_str().to_string()
-            }
-        })
-        .to_string()
-}
+/// Build the output file name from `filename_template`, or from the source
+/// file name with the optional date prefix when no template is set
+pub fn create_writing_name(frontmatter: &Value, config: &Config, writing: &Writing) -> String {
+    let ext = if config.use_mdx_format.unwrap_or(false) { "mdx" } else { "md" };
+    let publish_date = frontmatter["publishDate"].as_str().unwrap_or("");
 
-/// Convert all [[wikilinks]] to plain text
-pub fn strip_wikilinks(content: String) -> String {
-    let pattern = Regex::new(r"\[\[(.*?)\]\]").expect("Failed to create wikilink regex");
-    pattern
-        .replace_all(&content, |caps: &regex::Captures| {
-            if let Some(link) = caps.get(1) {
-                link.as_str().to_string()
-            } else {
-                caps.get(0).unwrap().as_str().to_string()
-            }
-        })
-        .to_string()
-}
+    if let Some(template) = config.filename_template.as_deref().filter(|t| !t.is_empty()) {
+        let date = NaiveDate::parse_from_str(publish_date, "%Y-%m-%d")
+            .ok()
+            .or(writing.publish_date);
+        return naming::render_file_name(template, writing, date, ext);
+    }
 
-/// Build the output file name based on frontmatter date prefix setting
-pub fn create_writing_name(frontmatter: &mut Value, config: &Config, writing_path: &str) -> String {
-    let mut writing_name = Path::new(writing_path)
-        .file_name()
+    let stem = Path::new(&writing.path)
+        .file_stem()
         .expect("Could not parse writing name")
-        .to_str()
-        .expect("Parsed writing name shouldn't be empty")
+        .to_string_lossy()
         .to_string();
-
-    let publish_date = frontmatter["publishDate"].as_str().unwrap_or("");
+    let mut writing_name = format!("{}.{}", stem, ext);
 
     if config.add_date_prefix.unwrap_or(false) && !publish_date.is_empty() {
         writing_name = format!("{}-{}", publish_date, writing_name);
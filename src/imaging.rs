@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader, ImageResult};
+use serde::{Deserialize, Serialize};
+
+use crate::asset::Asset;
+use crate::config::Config;
+use crate::staging::{StagingTransaction, WriteOutcome};
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// Keep the source format
+    #[default]
+    Original,
+    /// Re-encode PNG and JPEG as lossless WebP, which is often larger than
+    /// the JPEG it replaces
+    Webp,
+}
+
+/// Optional processing applied to images while they are transferred
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ImageOptions {
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    #[serde(default)]
+    pub format: Option<ImageFormat>,
+    #[serde(default)]
+    pub strip_metadata: Option<bool>,
+    #[serde(default)]
+    pub srcset_widths: Option<Vec<u32>>,
+}
+
+/// How a source image will appear in the target asset directory
+#[derive(Debug, Clone)]
+pub struct ProcessedImage {
+    pub file_name: String,
+    pub width: u32,
+    /// Smaller copies for `srcset`, as (width, file name)
+    pub variants: Vec<(u32, String)>,
+    /// Whether the main file is re-encoded rather than copied as is
+    reencode: bool,
+}
+
+impl ImageOptions {
+    /// Work out the output of an image without decoding it.
+    ///
+    /// Returns `None` for files that are not PNG/JPEG/WebP images, that cannot
+    /// be read, or that need no processing with the current options.
+    pub fn plan(&self, source: &Path) -> Option<ProcessedImage> {
+        let extension = source.extension()?.to_string_lossy().to_lowercase();
+        if !matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "webp") {
+            return None;
+        }
+        let (source_width, _) = oriented_dimensions(source).ok()?;
+        let stem = source.file_stem()?.to_string_lossy().to_string();
+
+        let to_webp = self.format.unwrap_or_default() == ImageFormat::Webp;
+        let output_extension = if to_webp { "webp".to_string() } else { extension };
+        let width = self.max_width.map_or(source_width, |max| source_width.min(max));
+        let resized = width < source_width;
+
+        let mut variant_widths: Vec<u32> = self
+            .srcset_widths
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|w| *w > 0 && *w < width)
+            .collect();
+        variant_widths.sort_unstable();
+        variant_widths.dedup();
+        let variants: Vec<(u32, String)> = variant_widths
+            .into_iter()
+            .map(|w| (w, format!("{}-{}w.{}", stem, w, output_extension)))
+            .collect();
+
+        let reencode = to_webp || resized || self.strip_metadata.unwrap_or(false);
+        if !reencode && variants.is_empty() {
+            return None;
+        }
+        Some(ProcessedImage {
+            file_name: format!("{}.{}", stem, output_extension),
+            width,
+            variants,
+            reencode,
+        })
+    }
+}
+
+impl ProcessedImage {
+    #[cfg(test)]
+    pub fn converted(file_name: &str, width: u32) -> Self {
+        Self {
+            file_name: file_name.to_string(),
+            width,
+            variants: Vec::new(),
+            reencode: true,
+        }
+    }
+
+    /// Queue the main image and its variants for `dest_dir`
+    pub fn write(
+        &self,
+        source: &Path,
+        dest_dir: &Path,
+        tx: &mut StagingTransaction,
+    ) -> io::Result<Vec<(PathBuf, WriteOutcome)>> {
+        let main_path = dest_dir.join(&self.file_name);
+        let mut written = Vec::new();
+
+        let image = open_oriented(source)
+            .map_err(|e| io::Error::other(format!("Cannot decode {}: {}", source.display(), e)))?;
+        let main_content = if self.reencode {
+            encode(&resize_to_width(&image, self.width), &main_path)?
+        } else {
+            fs::read(source)?
+        };
+        written.push((main_path.clone(), tx.write(&main_path, &main_content)?));
+
+        for (width, file_name) in &self.variants {
+            let variant_path = dest_dir.join(file_name);
+            let content = encode(&resize_to_width(&image, *width), &variant_path)?;
+            written.push((variant_path.clone(), tx.write(&variant_path, &content)?));
+        }
+        Ok(written)
+    }
+}
+
+/// Width and height as displayed, after the EXIF orientation is applied
+fn oriented_dimensions(source: &Path) -> ImageResult<(u32, u32)> {
+    let mut decoder = ImageReader::open(source)?.with_guessed_format()?.into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    Ok(match decoder.orientation()? {
+        Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Rotate90FlipH | Orientation::Rotate270FlipH => {
+            (height, width)
+        }
+        _ => (width, height),
+    })
+}
+
+/// Decode an image with its EXIF orientation applied to the pixels, since
+/// re-encoding drops the tag that told viewers to rotate it
+fn open_oriented(source: &Path) -> ImageResult<DynamicImage> {
+    let mut decoder = ImageReader::open(source)?.with_guessed_format()?.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn resize_to_width(image: &DynamicImage, width: u32) -> DynamicImage {
+    if image.width() <= width {
+        return image.clone();
+    }
+    let height = (image.height() as u64 * width as u64 / image.width() as u64).max(1) as u32;
+    image.resize_exact(width, height, FilterType::Lanczos3)
+}
+
+/// Encode in the format of the destination extension. Only pixel data is
+/// written, so EXIF and other metadata are dropped; images are expected to be
+/// decoded with `open_oriented` so they still display upright.
+fn encode(image: &DynamicImage, path: &Path) -> io::Result<Vec<u8>> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut buffer = Vec::new();
+    let result = match extension.as_str() {
+        "webp" => DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(WebPEncoder::new_lossless(&mut buffer)),
+        "jpg" | "jpeg" => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, JPEG_QUALITY)),
+        _ => image.write_with_encoder(PngEncoder::new(&mut buffer)),
+    };
+    result.map_err(|e| io::Error::other(format!("Cannot encode {}: {}", path.display(), e)))?;
+    Ok(buffer)
+}
+
+/// Processed output for every image asset, keyed by source file name.
+///
+/// Fails when two assets would end up with the same output name, such as
+/// `cat.png` and `cat.jpg` both converted to `cat.webp`.
+pub fn plan_images(config: &Config, asset_list: &[Asset]) -> io::Result<HashMap<String, ProcessedImage>> {
+    let Some(options) = &config.images else {
+        return Ok(HashMap::new());
+    };
+    let mut planned = HashMap::new();
+    // Output file name -> source file name
+    let mut outputs: HashMap<String, String> = HashMap::new();
+    for asset in asset_list {
+        let path = Path::new(&asset.asset_path);
+        let Some(source_name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let processed = options.plan(path);
+        let output_names: Vec<String> = match &processed {
+            Some(p) => std::iter::once(p.file_name.clone())
+                .chain(p.variants.iter().map(|(_, name)| name.clone()))
+                .collect(),
+            None => vec![source_name.clone()],
+        };
+        for output_name in output_names {
+            match outputs.get(&output_name) {
+                Some(other) if *other != source_name => {
+                    return Err(io::Error::other(format!(
+                        "{} and {} would both be staged as {}, rename one of them",
+                        other, source_name, output_name
+                    )));
+                }
+                _ => {
+                    outputs.insert(output_name, source_name.clone());
+                }
+            }
+        }
+        if let Some(processed) = processed {
+            planned.insert(source_name, processed);
+        }
+    }
+    Ok(planned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageEncoder, RgbImage};
+
+    /// A 4x2 JPEG whose EXIF orientation says to rotate it 90° clockwise
+    fn rotated_jpeg(path: &Path) {
+        let exif = vec![
+            0x49, 0x49, 0x2A, 0x00, 0x08, 0x00, 0x00, 0x00, // little-endian TIFF header
+            0x01, 0x00, // one entry
+            0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, // Orientation = 6
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        let image = RgbImage::new(4, 2);
+        let mut buffer = Vec::new();
+        let mut encoder = JpegEncoder::new(&mut buffer);
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder.write_image(&image, 4, 2, image::ExtendedColorType::Rgb8).unwrap();
+        fs::write(path, buffer).unwrap();
+    }
+
+    #[test]
+    fn applies_exif_orientation() {
+        let path = std::env::temp_dir().join(format!("lazydraft-rotated-{}.jpg", std::process::id()));
+        rotated_jpeg(&path);
+
+        assert_eq!(oriented_dimensions(&path).unwrap(), (2, 4));
+        let image = open_oriented(&path).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 4));
+
+        let options = ImageOptions {
+            format: Some(ImageFormat::Webp),
+            ..Default::default()
+        };
+        assert_eq!(options.plan(&path).unwrap().width, 2);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn reports_conversions_to_the_same_name() {
+        let dir = std::env::temp_dir().join(format!("lazydraft-imaging-collision-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let assets: Vec<Asset> = ["cat.png", "cat.jpg"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                RgbImage::new(4, 2).save(&path).unwrap();
+                Asset {
+                    asset_path: path.display().to_string(),
+                }
+            })
+            .collect();
+
+        let mut config: Config = toml::from_str("[images]\nformat = \"original\"").unwrap();
+        assert_eq!(plan_images(&config, &assets).unwrap().len(), 0);
+
+        config.images.as_mut().unwrap().format = Some(ImageFormat::Webp);
+        let error = plan_images(&config, &assets).unwrap_err();
+        assert_eq!(error.to_string(), "cat.png and cat.jpg would both be staged as cat.webp, rename one of them");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use chrono::NaiveDate;
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::{Captures, Regex};
+
+use crate::naming;
+use crate::writing::Writing;
+
+/// Default permalink when a project does not configure one
+pub const DEFAULT_PERMALINK: &str = "/{slug}";
+
+/// `[[...]]` links, with the `!` of embeds captured so they can be kept
+static WIKILINK_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(!?)\[\[([^\[\]]+)\]\]").expect("Failed to create wikilink regex"));
+
+struct LinkTarget {
+    title: String,
+    slug: String,
+    publish_date: Option<NaiveDate>,
+    is_published: bool,
+}
+
+/// Resolves `[[wikilinks]]` between writings to target-site URLs.
+///
+/// Built once per batch of stages from the writings already loaded, so
+/// staging many notes does not rescan the vault for each one.
+#[derive(Default)]
+pub struct LinkResolver {
+    index: HashMap<String, LinkTarget>,
+}
+
+/// The parts of a `[[note#heading|alias]]` link
+struct Wikilink<'a> {
+    note: &'a str,
+    heading: Option<&'a str>,
+    alias: Option<&'a str>,
+}
+
+impl<'a> Wikilink<'a> {
+    fn parse(inner: &'a str) -> Self {
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target, Some(alias.trim())),
+            None => (inner, None),
+        };
+        let (note, heading) = match target.split_once('#') {
+            Some((note, heading)) => (note.trim(), Some(heading.trim())),
+            None => (target.trim(), None),
+        };
+        Wikilink { note, heading, alias }
+    }
+
+    /// Text shown for the link, as Obsidian renders it
+    fn display_text(&self) -> String {
+        if let Some(alias) = self.alias.filter(|a| !a.is_empty()) {
+            return alias.to_string();
+        }
+        match self.heading {
+            Some(heading) if self.note.is_empty() => heading.trim_start_matches('^').to_string(),
+            Some(heading) => format!("{} > {}", self.note, heading.trim_start_matches('^')),
+            None => self.note.to_string(),
+        }
+    }
+
+    /// Anchor for heading links; block references (`#^id`) have none
+    fn anchor(&self) -> Option<String> {
+        self.heading
+            .filter(|h| !h.starts_with('^'))
+            .map(naming::slug)
+            .filter(|h| !h.is_empty())
+    }
+}
+
+impl LinkResolver {
+    pub fn new<'a>(writings: impl IntoIterator<Item = &'a Writing>) -> Self {
+        let mut index = HashMap::new();
+        for writing in writings {
+            let target = || LinkTarget {
+                title: writing.title.clone(),
+                slug: writing.slug.clone(),
+                publish_date: writing.publish_date,
+                is_published: !writing.is_draft,
+            };
+            let path = Path::new(&writing.path);
+            if let Some(stem) = path.file_stem() {
+                index.insert(stem.to_string_lossy().to_lowercase(), target());
+            }
+            if !writing.title.is_empty() {
+                index.entry(writing.title.to_lowercase()).or_insert_with(target);
+            }
+        }
+        Self { index }
+    }
+
+    fn lookup(&self, note: &str) -> Option<&LinkTarget> {
+        // Obsidian allows `folder/Note` and `Note.md`, the index is keyed by note name
+        let name = note.rsplit('/').next().unwrap_or(note);
+        let name = name.strip_suffix(".md").unwrap_or(name);
+        self.index.get(&name.to_lowercase())
+    }
+
+    fn permalink_for(target: &LinkTarget, permalink: &str) -> String {
+        let date = target
+            .publish_date
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let year = target.publish_date.map(|d| d.format("%Y").to_string()).unwrap_or_default();
+        let month = target.publish_date.map(|d| d.format("%m").to_string()).unwrap_or_default();
+        permalink
+            .replace("{slug}", &target.slug)
+            .replace("{date}", &date)
+            .replace("{year}", &year)
+            .replace("{month}", &month)
+    }
+
+    /// Rewrite every wikilink in `body` to a markdown link using the
+    /// `permalink` template.
+    ///
+    /// Links to missing or unpublished notes become plain text and are
+    /// reported through `warn`. Embeds (`![[...]]`) and links inside code
+    /// are left untouched.
+    pub fn resolve(&self, body: &str, permalink: &str, mut warn: impl FnMut(String)) -> String {
+        let code = code_ranges(body);
+        WIKILINK_PATTERN
+            .replace_all(body, |caps: &Captures| {
+                if !caps[1].is_empty() || in_code(&code, caps) {
+                    return caps[0].to_string();
+                }
+                let link = Wikilink::parse(&caps[2]);
+                let text = link.display_text();
+                let anchor = link.anchor().map(|a| format!("#{}", a)).unwrap_or_default();
+
+                if link.note.is_empty() {
+                    return format!("[{}]({})", text, anchor);
+                }
+                match self.lookup(link.note) {
+                    Some(target) if target.is_published => {
+                        format!("[{}]({}{})", text, Self::permalink_for(target, permalink), anchor)
+                    }
+                    Some(target) => {
+                        warn(format!("Link to unpublished writing '{}' left as plain text", target.title));
+                        text
+                    }
+                    None => {
+                        warn(format!("Link to unknown note '{}' left as plain text", link.note));
+                        text
+                    }
+                }
+            })
+            .to_string()
+    }
+}
+
+/// Replace every wikilink with its display text, keeping embeds and code
+pub fn strip_wikilinks(body: &str) -> String {
+    let code = code_ranges(body);
+    WIKILINK_PATTERN
+        .replace_all(body, |caps: &Captures| {
+            if caps[1].is_empty() && !in_code(&code, caps) {
+                Wikilink::parse(&caps[2]).display_text()
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .to_string()
+}
+
+/// Byte ranges of code spans and code blocks, where `[[...]]` is an example
+/// rather than a link
+fn code_ranges(body: &str) -> Vec<Range<usize>> {
+    Parser::new(body)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Code(_) | Event::Start(Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+fn in_code(code: &[Range<usize>], caps: &Captures) -> bool {
+    let start = caps.get(0).map_or(0, |m| m.start());
+    code.iter().any(|range| range.contains(&start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writing(path: &str, title: &str, slug: &str, is_draft: bool) -> Writing {
+        Writing {
+            path: path.to_string(),
+            title: title.to_string(),
+            is_draft,
+            publish_date: NaiveDate::from_ymd_opt(2024, 3, 9),
+            slug: slug.to_string(),
+            slug_collision: None,
+            diagnostic: None,
+        }
+    }
+
+    fn resolver() -> LinkResolver {
+        LinkResolver::new(&[
+            writing("vault/Rust Tips.md", "Rust Tips", "rust-tips", false),
+            writing("vault/notes/Garden.md", "My Garden", "garden", false),
+            writing("vault/Ideas.md", "Ideas", "ideas", true),
+        ])
+    }
+
+    fn resolve(body: &str) -> (String, Vec<String>) {
+        let mut warnings = Vec::new();
+        let output = resolver().resolve(body, DEFAULT_PERMALINK, |w| warnings.push(w));
+        (output, warnings)
+    }
+
+    #[test]
+    fn resolves_by_file_name_and_title() {
+        assert_eq!(resolve("See [[Rust Tips]].").0, "See [Rust Tips](/rust-tips).");
+        assert_eq!(resolve("See [[my garden]].").0, "See [my garden](/garden).");
+        assert_eq!(resolve("[[notes/Garden.md]]").0, "[notes/Garden.md](/garden)");
+    }
+
+    #[test]
+    fn keeps_alias_and_heading_anchor() {
+        assert_eq!(resolve("[[Rust Tips|tips]]").0, "[tips](/rust-tips)");
+        assert_eq!(
+            resolve("[[Rust Tips#Error Handling]]").0,
+            "[Rust Tips > Error Handling](/rust-tips#error-handling)"
+        );
+        assert_eq!(resolve("[[#Setup]]").0, "[Setup](#setup)");
+        assert_eq!(resolve("[[Rust Tips#^block1]]").0, "[Rust Tips > block1](/rust-tips)");
+    }
+
+    #[test]
+    fn unresolvable_links_become_text_with_a_warning() {
+        let (output, warnings) = resolve("[[Ideas]] and [[Missing]]");
+        assert_eq!(output, "Ideas and Missing");
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("unpublished"));
+        assert!(warnings[1].contains("unknown note 'Missing'"));
+    }
+
+    #[test]
+    fn embeds_are_untouched() {
+        assert_eq!(resolve("![[photo.png]]").0, "![[photo.png]]");
+        assert_eq!(strip_wikilinks("![[photo.png]] [[Rust Tips|tips]]"), "![[photo.png]] tips");
+    }
+
+    #[test]
+    fn links_in_code_are_untouched() {
+        let body = "Write `[[Rust Tips]]` to link.\n\n```markdown\n[[Rust Tips|tips]]\n```\n\n    [[Missing]]\n\nSee [[Rust Tips]].";
+        let (output, warnings) = resolve(body);
+        assert_eq!(
+            output,
+            "Write `[[Rust Tips]]` to link.\n\n```markdown\n[[Rust Tips|tips]]\n```\n\n    [[Missing]]\n\nSee [Rust Tips](/rust-tips)."
+        );
+        assert!(warnings.is_empty());
+        assert_eq!(strip_wikilinks("`[[a|b]]` [[a|b]]"), "`[[a|b]]` b");
+    }
+
+    #[test]
+    fn permalink_placeholders() {
+        let output = resolver().resolve("[[Rust Tips]]", "/{year}/{month}/{date}/{slug}/", |_| {});
+        assert_eq!(output, "[Rust Tips](/2024/03/2024-03-09/rust-tips/)");
+    }
+}
@@ -1,16 +1,29 @@
 use command::{parse_command, Command};
-use project::validate_config;
+use project::validate_active_project;
 
+mod activity;
 mod asset;
 mod cli;
 mod command;
 mod commands;
 mod config;
 mod dashboard;
+mod discovery;
+mod doctor;
+mod document;
+mod embed;
 mod frontmatter;
+mod imaging;
+mod links;
+mod naming;
+mod preset;
+mod preview;
 mod project;
+mod staging;
+mod transform;
 mod tui;
 mod views;
+mod watch;
 mod writing;
 
 fn main() {
@@ -21,6 +34,10 @@ fn main() {
         Some(command) => match command {
             Command::Status => dispatch_status(),
             Command::Stage(options) => dispatch_stage(options),
+            Command::Unstage(options) => dispatch_unstage(options),
+            Command::Publish(options) => dispatch_publish(options),
+            Command::Preview(options) => dispatch_preview(options),
+            Command::Doctor { project } => dispatch_doctor(project),
             Command::Config => commands::execute_config_command(command_args),
             Command::Info => commands::execute_info_command(),
             Command::Project(cmd) => dispatch_project(cmd),
@@ -37,10 +54,10 @@ fn main() {
 }
 
 fn dispatch_status() {
-    match validate_config() {
-        Ok(config) => {
-            commands::check_config_for_empty_fields(&config);
-            if let Err(err) = commands::execute_status_command(&config) {
+    match validate_active_project() {
+        Ok(project) => {
+            commands::check_config_for_empty_fields(&project.config);
+            if let Err(err) = commands::execute_status_command(&project) {
                 commands::exit_with_message(&err.to_string());
             }
         }
@@ -52,10 +69,10 @@ fn dispatch_status() {
 }
 
 fn dispatch_stage(options: command::StageOptions) {
-    match validate_config() {
-        Ok(config) => {
-            commands::check_config_for_empty_fields(&config);
-            if let Err(err) = commands::execute_stage_command(&config, options) {
+    match validate_active_project() {
+        Ok(project) => {
+            commands::check_config_for_empty_fields(&project.config);
+            if let Err(err) = commands::execute_stage_command(&project, options) {
                 commands::exit_with_message(&err.to_string());
             }
         }
@@ -66,6 +83,57 @@ fn dispatch_stage(options: command::StageOptions) {
     }
 }
 
+fn dispatch_unstage(options: command::UnstageOptions) {
+    match validate_active_project() {
+        Ok(project) => {
+            commands::check_config_for_empty_fields(&project.config);
+            if let Err(err) = commands::execute_unstage_command(&project, options) {
+                commands::exit_with_message(&err);
+            }
+        }
+        Err(e) => {
+            cli::error(&format!("Error: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn dispatch_publish(options: command::PublishOptions) {
+    match validate_active_project() {
+        Ok(project) => {
+            commands::check_config_for_empty_fields(&project.config);
+            if let Err(err) = commands::execute_publish_command(&project, options) {
+                commands::exit_with_message(&err);
+            }
+        }
+        Err(e) => {
+            cli::error(&format!("Error: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn dispatch_preview(options: command::PreviewOptions) {
+    match validate_active_project() {
+        Ok(project) => {
+            commands::check_config_for_empty_fields(&project.config);
+            if let Err(err) = commands::execute_preview_command(&project, options) {
+                commands::exit_with_message(&err);
+            }
+        }
+        Err(e) => {
+            cli::error(&format!("Error: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn dispatch_doctor(project: Option<String>) {
+    if let Err(err) = commands::execute_doctor_command(project) {
+        commands::exit_with_message(&err);
+    }
+}
+
 fn dispatch_project(cmd: command::ProjectCommand) {
     if let Err(err) = commands::execute_project_command(cmd) {
         commands::exit_with_message(&err);
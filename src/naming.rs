@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate};
+use deunicode::deunicode;
+
+use crate::writing::Writing;
+
+/// URL-safe slug: transliterated to ASCII, lowercase, words joined by `-`.
+/// `Crème Brûlée 🍮` becomes `creme-brulee-custard`.
+pub fn slug(text: &str) -> String {
+    deunicode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Slug for a note: its frontmatter `slug`, else its title, else its file name
+pub fn writing_slug(explicit: Option<&str>, title: &str, path: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let slug = [explicit.unwrap_or_default(), title, stem.as_str()]
+        .into_iter()
+        .map(slug)
+        .find(|s| !s.is_empty());
+    slug.unwrap_or_else(|| "untitled".to_string())
+}
+
+/// Make slugs unique across the project. Notes sharing a slug keep it in path
+/// order for the first one and get `-2`, `-3`, ... for the rest. Safe to run
+/// again after the list changed.
+pub fn dedupe_slugs(writings: &mut [Writing]) {
+    for writing in writings.iter_mut() {
+        if let Some(wanted) = writing.slug_collision.take() {
+            writing.slug = wanted;
+        }
+    }
+    let mut by_slug: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, writing) in writings.iter().enumerate() {
+        by_slug.entry(writing.slug.clone()).or_default().push(index);
+    }
+    let mut taken: HashSet<String> = by_slug.keys().cloned().collect();
+
+    let mut groups: Vec<Vec<usize>> = by_slug.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort_by(|a, b| writings[a[0]].slug.cmp(&writings[b[0]].slug));
+    for mut group in groups {
+        group.sort_by(|&a, &b| writings[a].path.cmp(&writings[b].path));
+        for &index in &group[1..] {
+            let base = writings[index].slug.clone();
+            let mut n = 2;
+            while taken.contains(&format!("{}-{}", base, n)) {
+                n += 1;
+            }
+            let unique = format!("{}-{}", base, n);
+            taken.insert(unique.clone());
+            writings[index].slug_collision = Some(base);
+            writings[index].slug = unique;
+        }
+    }
+}
+
+/// Expand a `filename_template` such as `{date}-{slug}.{ext}`.
+///
+/// Placeholders: `{slug}`, `{name}` (source file name without extension),
+/// `{date}`, `{year}`, `{month}`, `{day}` and `{ext}`. Date placeholders
+/// collapse together with an adjacent `-` or `_` when the note has no date.
+pub fn render_file_name(template: &str, writing: &Writing, date: Option<NaiveDate>, ext: &str) -> String {
+    let name = Path::new(&writing.path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let date_parts = [
+        ("{date}", date.map(|d| d.format("%Y-%m-%d").to_string())),
+        ("{year}", date.map(|d| d.year().to_string())),
+        ("{month}", date.map(|d| format!("{:02}", d.month()))),
+        ("{day}", date.map(|d| format!("{:02}", d.day()))),
+    ];
+
+    let mut file_name = template.to_string();
+    for (placeholder, value) in date_parts {
+        match value {
+            Some(value) => file_name = file_name.replace(placeholder, &value),
+            None => {
+                for separator in ["-", "_"] {
+                    file_name = file_name
+                        .replace(&format!("{}{}", placeholder, separator), "")
+                        .replace(&format!("{}{}", separator, placeholder), "");
+                }
+                file_name = file_name.replace(placeholder, "");
+            }
+        }
+    }
+    file_name
+        .replace("{slug}", &writing.slug)
+        .replace("{name}", &name)
+        .replace("{ext}", ext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writing(path: &str, slug: &str) -> Writing {
+        Writing {
+            path: path.to_string(),
+            title: String::new(),
+            is_draft: true,
+            publish_date: None,
+            slug: slug.to_string(),
+            slug_collision: None,
+            diagnostic: None,
+        }
+    }
+
+    #[test]
+    fn slugs_are_ascii_words() {
+        assert_eq!(slug("Crème Brûlée 🍮"), "creme-brulee-custard");
+        assert_eq!(slug("  Hello, World!  "), "hello-world");
+        assert_eq!(slug("Rust & C++ in 2024"), "rust-c-in-2024");
+        assert_eq!(slug("!!!"), "");
+    }
+
+    #[test]
+    fn writing_slug_falls_back_to_title_then_file_name() {
+        assert_eq!(writing_slug(Some("Custom"), "Title", "notes/File.md"), "custom");
+        assert_eq!(writing_slug(Some(""), "My Title", "notes/File.md"), "my-title");
+        assert_eq!(writing_slug(None, "", "notes/My File.md"), "my-file");
+        assert_eq!(writing_slug(None, "🍮", "notes/???.md"), "custard");
+        assert_eq!(writing_slug(None, "", "notes/!!!.md"), "untitled");
+    }
+
+    #[test]
+    fn dedupe_slugs_numbers_later_paths() {
+        let mut writings = vec![
+            writing("b.md", "post"),
+            writing("a.md", "post"),
+            writing("c.md", "post-2"),
+            writing("d.md", "post"),
+        ];
+        dedupe_slugs(&mut writings);
+        let slugs: Vec<&str> = writings.iter().map(|w| w.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["post-3", "post", "post-2", "post-4"]);
+        assert_eq!(writings[0].slug_collision.as_deref(), Some("post"));
+        assert_eq!(writings[1].slug_collision, None);
+
+        // Running again after a note left gives the slugs back
+        writings.remove(1);
+        dedupe_slugs(&mut writings);
+        let slugs: Vec<&str> = writings.iter().map(|w| w.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["post", "post-2", "post-3"]);
+    }
+
+    #[test]
+    fn render_file_name_fills_placeholders() {
+        let writing = writing("vault/My Note.md", "my-note");
+        let date = NaiveDate::from_ymd_opt(2024, 3, 9);
+        assert_eq!(
+            render_file_name("{date}-{slug}.{ext}", &writing, date, "md"),
+            "2024-03-09-my-note.md"
+        );
+        assert_eq!(
+            render_file_name("{year}/{month}/{day}_{name}.{ext}", &writing, date, "mdx"),
+            "2024/03/09_My Note.mdx"
+        );
+    }
+
+    #[test]
+    fn render_file_name_drops_missing_dates() {
+        let writing = writing("vault/note.md", "note");
+        assert_eq!(render_file_name("{date}-{slug}.{ext}", &writing, None, "md"), "note.md");
+        assert_eq!(render_file_name("{slug}_{year}.{ext}", &writing, None, "md"), "note.md");
+    }
+}
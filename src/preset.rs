@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::config::{Config, FrontmatterConfig, OutputLayout};
+use crate::document::FrontmatterFormat;
+
+/// Starting configuration for a static site generator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Hugo,
+    Jekyll,
+    Astro,
+    Zola,
+    Eleventy,
+}
+
+pub const PRESET_NAMES: &[&str] = &["hugo", "jekyll", "astro", "zola", "eleventy"];
+
+/// Where a generator expects posts and images, relative to the site root
+struct SiteLayout {
+    content_dir: &'static str,
+    asset_dir: &'static str,
+    asset_prefix: &'static str,
+    format: FrontmatterFormat,
+    date_key: &'static str,
+    /// Jekyll only reads posts named `YYYY-MM-DD-title`
+    filename_template: Option<&'static str>,
+    output: OutputLayout,
+}
+
+impl Preset {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "hugo" => Ok(Preset::Hugo),
+            "jekyll" => Ok(Preset::Jekyll),
+            "astro" => Ok(Preset::Astro),
+            "zola" => Ok(Preset::Zola),
+            "eleventy" | "11ty" => Ok(Preset::Eleventy),
+            _ => Err(format!(
+                "Unknown preset '{}'. Available presets: {}",
+                name,
+                PRESET_NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Hugo => "hugo",
+            Preset::Jekyll => "jekyll",
+            Preset::Astro => "astro",
+            Preset::Zola => "zola",
+            Preset::Eleventy => "eleventy",
+        }
+    }
+
+    fn layout(&self) -> SiteLayout {
+        match self {
+            Preset::Hugo => SiteLayout {
+                content_dir: "content/posts",
+                asset_dir: "static/images",
+                asset_prefix: "/images",
+                format: FrontmatterFormat::Toml,
+                date_key: "date",
+                filename_template: None,
+                output: OutputLayout::Bundle,
+            },
+            Preset::Jekyll => SiteLayout {
+                content_dir: "_posts",
+                asset_dir: "assets/images",
+                asset_prefix: "/assets/images",
+                format: FrontmatterFormat::Yaml,
+                date_key: "date",
+                filename_template: Some("{date}-{slug}.{ext}"),
+                output: OutputLayout::Flat,
+            },
+            Preset::Astro => SiteLayout {
+                content_dir: "src/content/blog",
+                asset_dir: "public/images",
+                asset_prefix: "/images",
+                format: FrontmatterFormat::Yaml,
+                date_key: "pubDate",
+                filename_template: None,
+                output: OutputLayout::Bundle,
+            },
+            Preset::Zola => SiteLayout {
+                content_dir: "content/blog",
+                asset_dir: "static/images",
+                asset_prefix: "/images",
+                format: FrontmatterFormat::Toml,
+                date_key: "date",
+                filename_template: None,
+                output: OutputLayout::Flat,
+            },
+            Preset::Eleventy => SiteLayout {
+                content_dir: "content/blog",
+                asset_dir: "public/img",
+                asset_prefix: "/img",
+                format: FrontmatterFormat::Yaml,
+                date_key: "date",
+                filename_template: None,
+                output: OutputLayout::Flat,
+            },
+        }
+    }
+
+    /// Fill in the generator's conventions, with target paths under `site_root`.
+    /// Source settings and any existing key mappings are left alone.
+    pub fn apply(&self, config: &mut Config, site_root: &Path) {
+        let layout = self.layout();
+        let path = |dir: &str| Some(site_root.join(dir).display().to_string());
+
+        config.target_dir = path(layout.content_dir);
+        config.target_asset_dir = path(layout.asset_dir);
+        config.target_asset_prefix = Some(layout.asset_prefix.to_string());
+        config.target_hero_image_prefix = Some(layout.asset_prefix.to_string());
+        config.add_date_prefix = Some(false);
+        config.filename_template = layout.filename_template.map(str::to_string);
+        config.layout = Some(layout.output);
+        config.preset = Some(self.name().to_string());
+        config.remove_draft_on_stage = Some(true);
+        config.use_mdx_format = Some(false);
+
+        let frontmatter = config.frontmatter.get_or_insert_with(FrontmatterConfig::default);
+        frontmatter.format = Some(layout.format);
+        frontmatter
+            .map
+            .target
+            .entry("publishDate".to_string())
+            .or_insert_with(|| layout.date_key.to_string());
+    }
+
+    /// A warning for staged file names the generator would silently skip
+    pub fn check_file_name(&self, file_name: &str) -> Option<String> {
+        match self {
+            Preset::Jekyll if !has_date_prefix(file_name) => Some(format!(
+                "Jekyll ignores posts without a YYYY-MM-DD- prefix; set a publish date so '{}' gets one",
+                file_name
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn has_date_prefix(file_name: &str) -> bool {
+    file_name
+        .get(..11)
+        .and_then(|prefix| prefix.strip_suffix('-'))
+        .is_some_and(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::create_writing_name;
+    use crate::writing::Writing;
+
+    fn jekyll_name(publish_date: Option<NaiveDate>) -> String {
+        let mut config: Config = toml::from_str("source_dir = \"vault\"").unwrap();
+        Preset::Jekyll.apply(&mut config, Path::new("site"));
+        assert_eq!(config.preset.as_deref(), Some("jekyll"));
+        let writing = Writing {
+            path: "vault/Hello.md".to_string(),
+            title: "Hello".to_string(),
+            is_draft: false,
+            publish_date,
+            slug: "hello".to_string(),
+            slug_collision: None,
+            diagnostic: None,
+        };
+        create_writing_name(&serde_yaml::Value::Null, &config, &writing)
+    }
+
+    #[test]
+    fn jekyll_accepts_dated_posts() {
+        let name = jekyll_name(NaiveDate::from_ymd_opt(2024, 3, 9));
+        assert_eq!(name, "2024-03-09-hello.md");
+        assert_eq!(Preset::Jekyll.check_file_name(&name), None);
+    }
+
+    #[test]
+    fn jekyll_warns_about_posts_without_a_date() {
+        let name = jekyll_name(None);
+        assert_eq!(name, "hello.md");
+        let warning = Preset::Jekyll.check_file_name(&name).unwrap();
+        assert!(warning.contains("'hello.md'"));
+        assert_eq!(Preset::Hugo.check_file_name(&name), None);
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::escape::escape_html;
+use pulldown_cmark::{html, Options, Parser};
+use serde_yaml::Value;
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::asset::get_asset_list_of_writing;
+use crate::cli;
+use crate::config::Config;
+use crate::document::Document;
+use crate::links::LinkResolver;
+use crate::staging::stage_files;
+use crate::watch::{self, SourceChange, SourceWatcher};
+use crate::writing::{find_writing, scan_writings, Writing};
+
+/// Port used when `--port` is not given
+pub const DEFAULT_PORT: u16 = 4040;
+
+/// Polled by every page; the answer changes whenever the preview is rebuilt
+const VERSION_PATH: &str = "/__lazydraft/version";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Name of the temporary preview directories, followed by the process id
+const ROOT_PREFIX: &str = "lazydraft-preview-";
+
+/// Kept locked inside a preview directory while its preview runs
+const LOCK_FILE: &str = ".lock";
+
+/// One writing staged into the preview directory
+#[derive(Debug, Clone)]
+struct PreviewPage {
+    source: String,
+    title: String,
+    is_draft: bool,
+    url: String,
+    staged_path: PathBuf,
+}
+
+/// The temporary directory of a running preview, removed again on drop.
+///
+/// A second Ctrl-C exits without dropping anything, so the directory holds a
+/// locked file: directories whose lock is free belong to no running preview
+/// and are cleaned up by the next one.
+struct PreviewRoot {
+    path: PathBuf,
+    lock: Option<File>,
+}
+
+impl PreviewRoot {
+    fn create() -> Result<Self, String> {
+        remove_stale_roots();
+        let path = std::env::temp_dir().join(format!("{}{}", ROOT_PREFIX, std::process::id()));
+        fs::create_dir_all(&path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+        let lock_path = path.join(LOCK_FILE);
+        let lock = File::create(&lock_path).map_err(|e| format!("Cannot create {}: {}", lock_path.display(), e))?;
+        lock.try_lock().map_err(|e| format!("Cannot lock {}: {}", lock_path.display(), e))?;
+        Ok(PreviewRoot { path, lock: Some(lock) })
+    }
+}
+
+impl Drop for PreviewRoot {
+    fn drop(&mut self) {
+        // Release the lock first, some platforms refuse to delete open files
+        self.lock.take();
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Remove preview directories left behind by previews that were killed
+fn remove_stale_roots() {
+    let Ok(entries) = fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if !entry.file_name().to_string_lossy().starts_with(ROOT_PREFIX) {
+            continue;
+        }
+        // Without a lock file the directory may be a preview still starting up
+        let Ok(lock) = File::open(entry.path().join(LOCK_FILE)) else {
+            continue;
+        };
+        if lock.try_lock().is_ok() {
+            drop(lock);
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// The project's writings staged into a temporary directory, with the
+/// target directories of the config pointed into it
+struct PreviewSite {
+    root: PreviewRoot,
+    config: Config,
+    query: Option<String>,
+    pages: Mutex<Vec<PreviewPage>>,
+    version: AtomicU64,
+}
+
+impl PreviewSite {
+    fn new(config: &Config, query: Option<String>) -> Result<Self, String> {
+        let root = PreviewRoot::create()?;
+        let content_dir = root.path.join("content");
+        let asset_dir = root.path.join("assets");
+        for dir in [&content_dir, &asset_dir] {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        }
+
+        let mut config = config.clone();
+        config.target_dir = Some(content_dir.display().to_string());
+        config.target_asset_dir = Some(asset_dir.display().to_string());
+        Ok(PreviewSite {
+            root,
+            config,
+            query,
+            pages: Mutex::new(Vec::new()),
+            version: AtomicU64::new(0),
+        })
+    }
+
+    fn content_dir(&self) -> PathBuf {
+        self.root.path.join("content")
+    }
+
+    fn asset_dir(&self) -> PathBuf {
+        self.root.path.join("assets")
+    }
+
+    /// Stage the previewed writings again, or only the notes in `stale`.
+    /// Open pages reload when anything changed; returns the titles of the
+    /// writings whose output changed.
+    fn rebuild(&self, stale: Option<&HashSet<PathBuf>>) -> Result<Vec<String>, String> {
+        let writings = scan_writings(&self.config).map_err(|e| e.to_string())?.writings;
+        let selected: Vec<&Writing> = match &self.query {
+            Some(query) => vec![find_writing(&writings, query)?],
+            None => writings.iter().filter(|w| w.is_draft).collect(),
+        };
+
+        let links = LinkResolver::new(&writings);
+        let previous = self.pages.lock().map(|pages| pages.clone()).map_err(|e| e.to_string())?;
+        let mut pages = Vec::new();
+        let mut updated = Vec::new();
+        for writing in selected {
+            let is_stale = stale.is_none_or(|paths| paths.contains(Path::new(&writing.path)));
+            if !is_stale {
+                // Writings that failed to stage wait for their next edit
+                pages.extend(previous.iter().find(|p| p.source == writing.path).cloned());
+                continue;
+            }
+            let staged = get_asset_list_of_writing(writing, &self.config)
+                .and_then(|assets| stage_files(&self.config, writing, &assets, &links));
+            match staged {
+                Ok(output) => {
+                    for warning in &output.rendered.warnings {
+                        cli::warn(&format!("{}: {}", writing.title, warning));
+                    }
+                    if output.stats.written > 0 {
+                        updated.push(writing.title.clone());
+                    }
+                    pages.push(PreviewPage {
+                        source: writing.path.clone(),
+                        title: writing.title.clone(),
+                        is_draft: writing.is_draft,
+                        url: self.page_url(&output.rendered.target_path, output.rendered.bundle_dir.as_deref()),
+                        staged_path: output.rendered.target_path,
+                    });
+                }
+                Err(e) => cli::error(&format!("Error staging {}: {}", writing.title, e)),
+            }
+        }
+
+        let mut current = self.pages.lock().map_err(|e| e.to_string())?;
+        let listing_changed = current.len() != pages.len()
+            || current.iter().zip(&pages).any(|(a, b)| a.url != b.url || a.title != b.title);
+        if listing_changed || !updated.is_empty() {
+            self.version.fetch_add(1, Ordering::Relaxed);
+        }
+        *current = pages;
+        Ok(updated)
+    }
+
+    /// `/<path under target_dir>/`, without the file extension or the
+    /// bundle's `index` file, so relative asset links resolve
+    fn page_url(&self, target_path: &Path, bundle_dir: Option<&Path>) -> String {
+        let page = bundle_dir.map(Path::to_path_buf).unwrap_or_else(|| target_path.with_extension(""));
+        let relative = page.strip_prefix(self.content_dir()).unwrap_or(&page);
+        let segments: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        format!("/{}/", segments.join("/"))
+    }
+
+    fn page(&self, url: &str) -> Option<PreviewPage> {
+        let wanted = format!("{}/", url.trim_end_matches('/'));
+        let pages = self.pages.lock().ok()?;
+        pages.iter().find(|page| page.url == wanted).cloned()
+    }
+
+    /// A staged asset, looked up under `target_asset_prefix` first and then in
+    /// the staged content, where bundles keep theirs
+    fn static_file(&self, url: &str) -> Option<PathBuf> {
+        let prefix = self.config.target_asset_prefix.clone().unwrap_or_default();
+        let prefix = prefix.trim_end_matches('/');
+        let mut candidates = Vec::new();
+        if !prefix.is_empty() {
+            if let Some(rest) = url.strip_prefix(prefix) {
+                candidates.push(self.asset_dir().join(relative_path(rest)?));
+            }
+        }
+        let relative = relative_path(url)?;
+        candidates.push(self.content_dir().join(&relative));
+        if let Some(name) = relative.file_name() {
+            // Transforms with their own prefix, such as `image_embeds`
+            candidates.push(self.asset_dir().join(name));
+        }
+        candidates.into_iter().find(|path| path.is_file())
+    }
+}
+
+/// A URL path as a relative file path, refusing anything that climbs out
+fn relative_path(url: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(url.trim_start_matches('/'));
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_)))
+        .then_some(path)
+}
+
+/// Stage the writings into a temporary directory and serve them as HTML on
+/// localhost until Ctrl-C, rebuilding whenever the vault changes
+pub fn serve(config: &Config, query: Option<String>, port: u16) -> Result<(), String> {
+    let site = Arc::new(PreviewSite::new(config, query)?);
+    run(config, &site, port)
+}
+
+fn run(config: &Config, site: &Arc<PreviewSite>, port: u16) -> Result<(), String> {
+    // Registered first, so a Ctrl-C during the first stage still cleans up
+    let stop = watch::shutdown_flag().map_err(|e| e.to_string())?;
+    cli::info("Staging writings for preview...");
+    site.rebuild(None)?;
+    let server = Server::http(("127.0.0.1", port)).map_err(|e| format!("Cannot listen on port {}: {}", port, e))?;
+    let watcher = spawn_watcher(config, Arc::clone(site), Arc::clone(&stop))?;
+    let page_count = site.pages.lock().map(|pages| pages.len()).unwrap_or_default();
+    cli::success(&format!("Previewing {} writing(s) at http://127.0.0.1:{}/", page_count, port));
+    cli::info("Pages reload when the vault changes. Press Ctrl-C to stop.");
+
+    while !stop.load(Ordering::Relaxed) {
+        match server.recv_timeout(POLL_INTERVAL) {
+            Ok(Some(request)) => handle_request(site, request),
+            Ok(None) => {}
+            Err(e) => {
+                stop.store(true, Ordering::Relaxed);
+                let _ = watcher.join();
+                return Err(format!("Preview server stopped: {}", e));
+            }
+        }
+    }
+    let _ = watcher.join();
+    cli::info("Preview stopped");
+    Ok(())
+}
+
+/// Watch the vault with the same rules as `stage --continuous` and rebuild
+/// once changes have settled
+fn spawn_watcher(
+    config: &Config,
+    site: Arc<PreviewSite>,
+    stop: Arc<AtomicBool>,
+) -> Result<thread::JoinHandle<()>, String> {
+    let mut watcher = SourceWatcher::new(config)?;
+    Ok(thread::spawn(move || {
+        let result = watcher.run(&stop, |batch| match batch {
+            Ok(changes) => rebuild(&site, &changes),
+            Err(e) => cli::error(&format!("Watch error: {}", e)),
+        });
+        if let Err(e) = result {
+            cli::error(&e);
+        }
+    }))
+}
+
+fn rebuild(site: &PreviewSite, changes: &[SourceChange]) {
+    // A changed image can be used by any page, so everything is restaged
+    let mut stale = Some(HashSet::new());
+    for change in changes {
+        match (change, &mut stale) {
+            (SourceChange::AssetChanged(_), _) => stale = None,
+            (SourceChange::Changed(path) | SourceChange::Renamed { to: path, .. }, Some(paths)) => {
+                paths.insert(path.clone());
+            }
+            _ => {}
+        }
+    }
+    match site.rebuild(stale.as_ref()) {
+        Ok(updated) => {
+            for title in updated {
+                cli::success(&format!("Updated {}", title));
+            }
+        }
+        Err(e) => cli::error(&format!("Preview rebuild failed: {}", e)),
+    }
+}
+
+fn handle_request(site: &PreviewSite, request: Request) {
+    let raw = request.url().split(['?', '#']).next().unwrap_or("/");
+    let url = percent_decode_str(raw).decode_utf8_lossy().to_string();
+    if url == VERSION_PATH {
+        let version = site.version.load(Ordering::Relaxed).to_string();
+        return respond(request, 200, "text/plain; charset=utf-8", version.into_bytes());
+    }
+    if url == "/" {
+        return respond(request, 200, "text/html; charset=utf-8", index_html(site).into_bytes());
+    }
+    if let Some(page) = site.page(&url) {
+        return respond(request, 200, "text/html; charset=utf-8", page_html(site, &page).into_bytes());
+    }
+    match site.static_file(&url).and_then(|path| fs::read(&path).ok().map(|data| (path, data))) {
+        Some((path, data)) => respond(request, 200, content_type(&path), data),
+        None => respond(request, 404, "text/plain; charset=utf-8", b"Not found".to_vec()),
+    }
+}
+
+fn respond(request: Request, status: u16, content_type: &str, body: Vec<u8>) {
+    let mut response = Response::from_data(body).with_status_code(status);
+    for (name, value) in [("Content-Type", content_type), ("Cache-Control", "no-store")] {
+        if let Ok(header) = Header::from_bytes(name, value) {
+            response.add_header(header);
+        }
+    }
+    let _ = request.respond(response);
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "mp4" => "video/mp4",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+// ── HTML ────────────────────────────────────────────────────────────────────
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    let _ = escape_html(&mut escaped, text);
+    escaped
+}
+
+fn markdown_to_html(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new_ext(markdown, options));
+    output
+}
+
+fn index_html(site: &PreviewSite) -> String {
+    let pages = site.pages.lock().map(|pages| pages.clone()).unwrap_or_default();
+    let body = if pages.is_empty() {
+        "<p>No drafts to preview.</p>".to_string()
+    } else {
+        format!("<ul class=\"pages\">{}</ul>", page_links(&pages, None))
+    };
+    layout(site, "Preview", "", &format!("<h1>Preview</h1>{}", body))
+}
+
+fn page_html(site: &PreviewSite, page: &PreviewPage) -> String {
+    let pages = site.pages.lock().map(|pages| pages.clone()).unwrap_or_default();
+    let nav = format!(
+        "<h2><a href=\"/\">Writings</a></h2><ul class=\"pages\">{}</ul>",
+        page_links(&pages, Some(&page.url))
+    );
+    let (sidebar, article) = match fs::read_to_string(&page.staged_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| Document::parse(&content).map_err(String::from))
+    {
+        Ok(document) => (
+            format!("{}<h2>Frontmatter</h2>{}", nav, frontmatter_html(&document.frontmatter)),
+            markdown_to_html(&document.body),
+        ),
+        Err(e) => (
+            nav,
+            format!("<p class=\"error\">Cannot read {}: {}</p>", escape(&page.staged_path.display().to_string()), escape(&e)),
+        ),
+    };
+    layout(site, &page.title, &sidebar, &format!("<article>{}</article>", article))
+}
+
+fn page_links(pages: &[PreviewPage], current: Option<&str>) -> String {
+    pages
+        .iter()
+        .map(|page| {
+            let class = if current == Some(page.url.as_str()) { " class=\"current\"" } else { "" };
+            let badge = if page.is_draft { " <span class=\"badge\">draft</span>" } else { "" };
+            format!("<li{}><a href=\"{}\">{}</a>{}</li>", class, escape(&page.url), escape(&page.title), badge)
+        })
+        .collect()
+}
+
+/// The staged frontmatter as a definition list; nested values are shown as YAML
+fn frontmatter_html(frontmatter: &Value) -> String {
+    let Value::Mapping(mapping) = frontmatter else {
+        return "<p class=\"empty\">None</p>".to_string();
+    };
+    let entries: String = mapping
+        .iter()
+        .map(|(key, value)| format!("<dt>{}</dt><dd>{}</dd>", escape(&scalar_text(key)), value_html(value)))
+        .collect();
+    format!("<dl>{}</dl>", entries)
+}
+
+fn value_html(value: &Value) -> String {
+    match value {
+        Value::Sequence(items) if items.iter().all(is_scalar) => {
+            escape(&items.iter().map(scalar_text).collect::<Vec<_>>().join(", "))
+        }
+        Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => {
+            let yaml = serde_yaml::to_string(value).unwrap_or_default();
+            format!("<pre>{}</pre>", escape(yaml.trim_end()))
+        }
+        scalar => escape(&scalar_text(scalar)),
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_))
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim_end().to_string(),
+    }
+}
+
+fn layout(site: &PreviewSite, title: &str, sidebar: &str, main: &str) -> String {
+    let version = site.version.load(Ordering::Relaxed);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} · lazydraft preview</title>
+<style>{style}</style>
+</head>
+<body>
+<aside>{sidebar}</aside>
+<main>{main}</main>
+<script>
+const version = "{version}";
+setInterval(async () => {{
+  try {{
+    const response = await fetch("{version_path}", {{ cache: "no-store" }});
+    if ((await response.text()) !== version) location.reload();
+  }} catch (e) {{}}
+}}, 1000);
+</script>
+</body>
+</html>
+"#,
+        title = escape(title),
+        style = STYLE,
+        sidebar = sidebar,
+        main = main,
+        version = version,
+        version_path = VERSION_PATH,
+    )
+}
+
+const STYLE: &str = "
+body { margin: 0; display: flex; font: 16px/1.6 -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; color: #222; }
+aside { width: 18rem; flex-shrink: 0; padding: 1.5rem; background: #f6f7f9; border-right: 1px solid #e2e4e8; min-height: 100vh; font-size: 14px; box-sizing: border-box; }
+aside h2 { font-size: 13px; text-transform: uppercase; letter-spacing: .05em; color: #666; margin: 1.5rem 0 .5rem; }
+aside h2:first-child { margin-top: 0; }
+aside a { color: inherit; }
+main { flex: 1; max-width: 46rem; padding: 2rem 3rem; }
+ul.pages { list-style: none; padding: 0; margin: 0; }
+ul.pages li { padding: .2rem 0; }
+ul.pages li.current { font-weight: 600; }
+.badge { font-size: 11px; background: #fde8b4; color: #7a5600; border-radius: 3px; padding: 0 .3rem; }
+dl { margin: 0; }
+dt { font-weight: 600; margin-top: .5rem; }
+dd { margin: 0; word-break: break-word; color: #444; }
+pre { background: #f0f1f3; padding: .75rem; overflow-x: auto; font-size: 13px; }
+dd pre { margin: .25rem 0; padding: .5rem; }
+code { background: #f0f1f3; padding: 0 .2rem; }
+pre code { background: none; padding: 0; }
+img { max-width: 100%; }
+blockquote { margin: 0; padding-left: 1rem; border-left: 3px solid #ddd; color: #555; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ddd; padding: .3rem .6rem; }
+.error { color: #b00020; }
+.empty { color: #888; }
+";
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::cli;
 use crate::config::{Config, ConfigResult};
+use crate::staging::StagingLedger;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ProjectConfig {
@@ -50,10 +51,19 @@ impl ProjectConfig {
                 auto_add_hero_img: Some(false),
                 remove_draft_on_stage: Some(false),
                 add_date_prefix: Some(false),
+                filename_template: None,
                 remove_wikilinks: Some(false),
                 trim_tags: Some(false),
                 tag_prefix: None,
                 use_mdx_format: Some(false),
+                asset_discovery: None,
+                layout: None,
+                debounce_ms: None,
+                pipeline: None,
+                discovery: None,
+                frontmatter: None,
+                images: None,
+                preset: None,
             },
         }
     }
@@ -114,14 +124,13 @@ impl ProjectManager {
         Ok(projects)
     }
 
-    pub fn create_project(&self, name: &str, description: Option<String>) -> ConfigResult<ProjectConfig> {
-        let project_path = self.projects_dir.join(format!("{}.toml", name));
+    pub fn create_project(&self, project: ProjectConfig) -> ConfigResult<ProjectConfig> {
+        let project_path = self.projects_dir.join(format!("{}.toml", project.name));
         
         if project_path.exists() {
-            return Err(format!("Project '{}' already exists", name));
+            return Err(format!("Project '{}' already exists", project.name));
         }
 
-        let project = ProjectConfig::new(name.to_string(), description);
         self.save_project(&project)?;
         Ok(project)
     }
@@ -159,10 +168,45 @@ impl ProjectManager {
 
         fs::remove_file(&project_path)
             .map_err(|e| format!("Failed to delete project file: {}", e))?;
+
+        let ledger_path = self.staging_ledger_path(name);
+        if ledger_path.exists() {
+            fs::remove_file(&ledger_path)
+                .map_err(|e| format!("Failed to delete staging ledger: {}", e))?;
+        }
         
         Ok(())
     }
 
+    fn staging_ledger_path(&self, name: &str) -> PathBuf {
+        self.projects_dir.join(format!("{}.staged.json", name))
+    }
+
+    pub fn load_staging_ledger(&self, name: &str) -> ConfigResult<StagingLedger> {
+        let ledger_path = self.staging_ledger_path(name);
+
+        if !ledger_path.exists() {
+            return Ok(StagingLedger::default());
+        }
+
+        let contents = fs::read_to_string(&ledger_path)
+            .map_err(|e| format!("Failed to read staging ledger: {}", e))?;
+
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse staging ledger: {}", e))
+    }
+
+    pub fn save_staging_ledger(&self, name: &str, ledger: &StagingLedger) -> ConfigResult<()> {
+        let ledger_path = self.staging_ledger_path(name);
+        let contents = serde_json::to_string_pretty(ledger)
+            .map_err(|e| format!("Failed to serialize staging ledger: {}", e))?;
+
+        fs::write(&ledger_path, contents)
+            .map_err(|e| format!("Failed to write staging ledger: {}", e))?;
+
+        Ok(())
+    }
+
     pub fn get_active_project(&self) -> ConfigResult<Option<String>> {
         let active_path = self.config_dir.join("active_project.toml");
         
@@ -248,7 +292,7 @@ pub fn get_project_manager() -> ConfigResult<ProjectManager> {
     ProjectManager::new()
 }
 
-pub fn validate_config() -> ConfigResult<Config> {
+pub fn validate_active_project() -> ConfigResult<ProjectConfig> {
     let project_manager = ProjectManager::new()?;
     
     project_manager.migrate_legacy_config()?;
@@ -273,5 +317,5 @@ pub fn validate_config() -> ConfigResult<Config> {
     project.update_last_used();
     project_manager.save_project(&project)?;
     
-    Ok(project.config)
+    Ok(project)
 }
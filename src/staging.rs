@@ -0,0 +1,631 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::asset::{transfer_asset_files, Asset};
+use crate::config::Config;
+use crate::links::LinkResolver;
+use crate::writing::{update_writing_content_and_transfer, RenderedWriting, Writing};
+
+/// A file produced by staging, with the hash of what was written
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StagedFile {
+    pub path: String,
+    pub content_hash: String,
+    /// The file existed before staging, so unstaging must not delete it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub overwritten: bool,
+}
+
+/// A writing that has been staged into the target directory
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StagedEntry {
+    pub source_path: String,
+    pub target_path: String,
+    pub content_hash: String,
+    pub staged_at: String,
+    #[serde(default)]
+    pub assets: Vec<StagedFile>,
+    /// Directory created for the bundle layout, removed on unstage once empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle_dir: Option<String>,
+}
+
+/// Files of a previous stage that a restage to a new target no longer writes
+#[derive(Debug, Default)]
+pub struct StaleFiles {
+    /// Deleted from the target directories
+    pub removed: Vec<String>,
+    /// Left in place because they changed since staging or could not be deleted
+    pub kept: Vec<String>,
+}
+
+impl StaleFiles {
+    /// One warning per previous output left in place
+    pub fn warnings(&self) -> impl Iterator<Item = String> + '_ {
+        self.kept
+            .iter()
+            .map(|path| format!("Previous output {} changed since staging and was left in place", path))
+    }
+}
+
+/// Per-project record of staged writings, persisted next to the project config
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct StagingLedger {
+    #[serde(default)]
+    pub entries: Vec<StagedEntry>,
+}
+
+impl StagedEntry {
+    pub fn new(
+        source_path: &str,
+        rendered: &RenderedWriting,
+        copied_assets: &[PathBuf],
+        overwritten_assets: &[PathBuf],
+    ) -> Self {
+        let assets = copied_assets
+            .iter()
+            .filter_map(|path| {
+                let content = fs::read(path).ok()?;
+                Some(StagedFile {
+                    path: path.display().to_string(),
+                    content_hash: hash_content(&content),
+                    overwritten: overwritten_assets.contains(path),
+                })
+            })
+            .collect();
+        Self {
+            source_path: source_path.to_string(),
+            target_path: rendered.target_path.display().to_string(),
+            content_hash: hash_content(rendered.content.as_bytes()),
+            staged_at: chrono::Utc::now().to_rfc3339(),
+            assets,
+            bundle_dir: rendered.bundle_dir.as_ref().map(|dir| dir.display().to_string()),
+        }
+    }
+}
+
+impl StagingLedger {
+    pub fn get(&self, source_path: &str) -> Option<&StagedEntry> {
+        self.entries.iter().find(|e| e.source_path == source_path)
+    }
+
+    pub fn contains(&self, source_path: &str) -> bool {
+        self.get(source_path).is_some()
+    }
+
+    /// Add an entry, replacing any previous entry for the same source.
+    ///
+    /// An asset that exists only because an earlier stage created it is still
+    /// counted as created, so restaging does not turn it into a kept file.
+    /// When the writing now goes to a different target, the files only the
+    /// previous stage produced are deleted rather than left untracked.
+    pub fn record(&mut self, mut entry: StagedEntry) -> StaleFiles {
+        for asset in entry.assets.iter_mut().filter(|a| a.overwritten) {
+            asset.overwritten = !self
+                .entries
+                .iter()
+                .flat_map(|e| &e.assets)
+                .any(|a| a.path == asset.path && !a.overwritten);
+        }
+        let stale = match self.get(&entry.source_path) {
+            Some(previous) if previous.target_path != entry.target_path => {
+                self.remove_stale_files(&previous.clone(), &entry)
+            }
+            _ => StaleFiles::default(),
+        };
+        self.remove(&entry.source_path);
+        self.entries.push(entry);
+        stale
+    }
+
+    fn remove_stale_files(&self, previous: &StagedEntry, current: &StagedEntry) -> StaleFiles {
+        let still_written: Vec<&str> = std::iter::once(current.target_path.as_str())
+            .chain(current.assets.iter().map(|a| a.path.as_str()))
+            .collect();
+        let mut stale = StaleFiles::default();
+        for file in self.owned_files(previous) {
+            if still_written.contains(&file.path.as_str()) || !Path::new(&file.path).exists() {
+                continue;
+            }
+            if !is_modified(&file) && fs::remove_file(&file.path).is_ok() {
+                stale.removed.push(file.path);
+            } else {
+                stale.kept.push(file.path);
+            }
+        }
+        if let Some(bundle_dir) = previous.bundle_dir.as_ref().filter(|dir| current.bundle_dir.as_ref() != Some(*dir)) {
+            // Only succeeds when nothing else was put in the bundle
+            let _ = fs::remove_dir(bundle_dir);
+        }
+        stale
+    }
+
+    pub fn remove(&mut self, source_path: &str) -> Option<StagedEntry> {
+        let pos = self.entries.iter().position(|e| e.source_path == source_path)?;
+        Some(self.entries.remove(pos))
+    }
+
+    /// Files staging produced for a writing that this writing alone owns.
+    ///
+    /// Assets that existed before staging, or that are also recorded for
+    /// another staged writing, are left out so that unstaging one post does not
+    /// delete files it did not create or break another post.
+    fn owned_files(&self, entry: &StagedEntry) -> Vec<StagedFile> {
+        let shared_assets: Vec<&str> = self
+            .entries
+            .iter()
+            .filter(|e| e.source_path != entry.source_path)
+            .flat_map(|e| e.assets.iter().map(|a| a.path.as_str()))
+            .collect();
+        let mut files = vec![StagedFile {
+            path: entry.target_path.clone(),
+            content_hash: entry.content_hash.clone(),
+            overwritten: false,
+        }];
+        files.extend(
+            entry
+                .assets
+                .iter()
+                .filter(|a| !a.overwritten && !shared_assets.contains(&a.path.as_str()))
+                .cloned(),
+        );
+        files
+    }
+
+    /// Staged files of a writing whose content changed since it was staged
+    pub fn modified_files(&self, source_path: &str) -> Vec<String> {
+        self.get(source_path)
+            .map(|entry| {
+                self.owned_files(entry)
+                    .into_iter()
+                    .filter(is_modified)
+                    .map(|f| f.path)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Delete the files staging produced for a writing and drop it from the ledger.
+    ///
+    /// Files whose content changed since staging are left alone unless `force`
+    /// is set. Returns the paths that were deleted.
+    pub fn unstage(&mut self, source_path: &str, force: bool) -> Result<Vec<String>, String> {
+        let entry = self
+            .get(source_path)
+            .cloned()
+            .ok_or_else(|| format!("{} is not staged", source_path))?;
+
+        if !force {
+            let modified = self.modified_files(source_path);
+            if !modified.is_empty() {
+                return Err(format!("Modified after staging: {}", modified.join(", ")));
+            }
+        }
+
+        let mut removed = Vec::new();
+        for file in self.owned_files(&entry) {
+            if Path::new(&file.path).exists() {
+                fs::remove_file(&file.path)
+                    .map_err(|e| format!("Failed to delete {}: {}", file.path, e))?;
+                removed.push(file.path);
+            }
+        }
+        if let Some(bundle_dir) = &entry.bundle_dir {
+            // Only succeeds when nothing else was put in the bundle
+            let _ = fs::remove_dir(bundle_dir);
+        }
+        self.remove(source_path);
+        Ok(removed)
+    }
+}
+
+/// Whether a staged file exists and no longer matches the hash recorded at staging
+fn is_modified(file: &StagedFile) -> bool {
+    match fs::read(&file.path) {
+        Ok(content) => hash_content(&content) != file.content_hash,
+        Err(_) => false,
+    }
+}
+
+/// Hex-encoded SHA-256 of the given content
+pub fn hash_content(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteOutcome {
+    Written,
+    Unchanged,
+}
+
+/// Files written for one stage. Content goes to temporary files next to each
+/// target and is only moved into place by `commit`, so a failure partway
+/// through leaves the target directories as they were.
+#[derive(Default)]
+pub struct StagingTransaction {
+    pending: Vec<PendingWrite>,
+    /// Directories created for the temporary files, outermost first
+    created_dirs: Vec<PathBuf>,
+}
+
+struct PendingWrite {
+    temp: PathBuf,
+    target: PathBuf,
+}
+
+impl StagingTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `content` for `path` unless the file already holds the same content,
+    /// so unchanged outputs keep their modification time.
+    pub fn write(&mut self, path: &Path, content: &[u8]) -> io::Result<WriteOutcome> {
+        if let Ok(existing) = fs::read(path) {
+            if hash_content(&existing) == hash_content(content) {
+                return Ok(WriteOutcome::Unchanged);
+            }
+        }
+        if let Some(parent) = path.parent() {
+            let missing = parent
+                .ancestors()
+                .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+            self.created_dirs.extend(missing.into_iter().rev());
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp = sibling_path(path, "tmp");
+        let mut file = fs::File::create(&temp)?;
+        let written = file.write_all(content).and_then(|_| file.sync_all());
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        if !self.pending.iter().any(|p| p.target == path) {
+            self.pending.push(PendingWrite {
+                temp,
+                target: path.to_path_buf(),
+            });
+        }
+        Ok(WriteOutcome::Written)
+    }
+
+    /// Move every queued file into place. If a rename fails, the files already
+    /// replaced are restored and the rest are discarded.
+    pub fn commit(mut self) -> io::Result<()> {
+        let mut applied: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+        for write in &self.pending {
+            match replace_file(&write.temp, &write.target) {
+                Ok(backup) => applied.push((write.target.clone(), backup)),
+                Err(e) => {
+                    for (target, backup) in applied.into_iter().rev() {
+                        let _ = match backup {
+                            Some(backup) => fs::rename(&backup, &target),
+                            None => fs::remove_file(&target),
+                        };
+                    }
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("Could not replace {}: {}", write.target.display(), e),
+                    ));
+                }
+            }
+        }
+
+        for backup in applied.into_iter().filter_map(|(_, backup)| backup) {
+            let _ = fs::remove_file(backup);
+        }
+        self.pending.clear();
+        self.created_dirs.clear();
+        Ok(())
+    }
+}
+
+impl Drop for StagingTransaction {
+    /// Uncommitted temporary files are removed, along with the directories
+    /// created for them
+    fn drop(&mut self) {
+        for write in &self.pending {
+            let _ = fs::remove_file(&write.temp);
+        }
+        for dir in self.created_dirs.iter().rev() {
+            // Only succeeds while empty, so directories filled since are kept
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+/// Rename `temp` over `target`, keeping a backup of the old target for rollback
+fn replace_file(temp: &Path, target: &Path) -> io::Result<Option<PathBuf>> {
+    let backup = if target.exists() {
+        let backup = sibling_path(target, "bak");
+        fs::hard_link(target, &backup).or_else(|_| fs::copy(target, &backup).map(|_| ()))?;
+        Some(backup)
+    } else {
+        None
+    };
+    if let Err(e) = fs::rename(temp, target) {
+        if let Some(backup) = &backup {
+            let _ = fs::remove_file(backup);
+        }
+        return Err(e);
+    }
+    Ok(backup)
+}
+
+/// Hidden file next to `path`, e.g. `.post.md.lazydraft-tmp`
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.lazydraft-{}", name, suffix))
+}
+
+/// Counts of what a stage did with its target files
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StageStats {
+    pub written: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+}
+
+impl StageStats {
+    pub fn record(&mut self, outcome: WriteOutcome) {
+        match outcome {
+            WriteOutcome::Written => self.written += 1,
+            WriteOutcome::Unchanged => self.unchanged += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: StageStats) {
+        self.written += other.written;
+        self.unchanged += other.unchanged;
+        self.skipped += other.skipped;
+    }
+}
+
+impl fmt::Display for StageStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} written / {} unchanged / {} skipped",
+            self.written, self.unchanged, self.skipped
+        )
+    }
+}
+
+/// What a committed stage produced
+pub struct StageOutput {
+    pub rendered: RenderedWriting,
+    pub asset_files: Vec<PathBuf>,
+    pub overwritten_assets: Vec<PathBuf>,
+    pub stats: StageStats,
+}
+
+/// Stage a writing and its assets as one transaction: either every file is
+/// updated or, on any error, the target directories are left untouched.
+pub fn stage_files(
+    config: &Config,
+    writing: &Writing,
+    asset_list: &[Asset],
+    links: &LinkResolver,
+) -> io::Result<StageOutput> {
+    let mut tx = StagingTransaction::new();
+    let (rendered, outcome) = update_writing_content_and_transfer(config, writing, asset_list, links, &mut tx)?;
+    let assets = transfer_asset_files(config, asset_list, &rendered, &mut tx)?;
+    tx.commit()?;
+
+    let mut stats = assets.stats;
+    stats.record(outcome);
+    Ok(StageOutput {
+        rendered,
+        asset_files: assets.files,
+        overwritten_assets: assets.overwritten,
+        stats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lazydraft-staging-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn stage(dir: &Path, source: &str, assets: &[(&str, bool)]) -> StagedEntry {
+        let target_path = dir.join(format!("{}.md", source));
+        let content = format!("# {}", source);
+        fs::write(&target_path, &content).unwrap();
+        let mut copied = Vec::new();
+        let mut overwritten = Vec::new();
+        for (name, existed) in assets {
+            let path = dir.join(name);
+            if !path.exists() {
+                fs::write(&path, name).unwrap();
+            }
+            if *existed {
+                overwritten.push(path.clone());
+            }
+            copied.push(path);
+        }
+        let rendered = RenderedWriting {
+            target_path,
+            bundle_dir: None,
+            content,
+            warnings: Vec::new(),
+        };
+        StagedEntry::new(source, &rendered, &copied, &overwritten)
+    }
+
+    #[test]
+    fn unstage_deletes_created_files() {
+        let dir = temp_dir("created");
+        let mut ledger = StagingLedger::default();
+        ledger.record(stage(&dir, "post", &[("cover.png", false)]));
+
+        let removed = ledger.unstage("post", false).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!dir.join("post.md").exists());
+        assert!(!dir.join("cover.png").exists());
+        assert!(!ledger.contains("post"));
+    }
+
+    #[test]
+    fn unstage_keeps_overwritten_assets() {
+        let dir = temp_dir("overwritten");
+        fs::write(dir.join("logo.png"), "site logo").unwrap();
+        let mut ledger = StagingLedger::default();
+        ledger.record(stage(&dir, "post", &[("logo.png", true)]));
+
+        ledger.unstage("post", false).unwrap();
+        assert!(!dir.join("post.md").exists());
+        assert!(dir.join("logo.png").exists());
+    }
+
+    #[test]
+    fn restaging_keeps_assets_created_by_an_earlier_stage() {
+        let dir = temp_dir("restage");
+        let mut ledger = StagingLedger::default();
+        ledger.record(stage(&dir, "post", &[("cover.png", false)]));
+        // The second stage sees the file it created the first time
+        ledger.record(stage(&dir, "post", &[("cover.png", true)]));
+
+        assert!(!ledger.get("post").unwrap().assets[0].overwritten);
+        ledger.unstage("post", false).unwrap();
+        assert!(!dir.join("cover.png").exists());
+    }
+
+    #[test]
+    fn unstage_keeps_assets_shared_with_another_writing() {
+        let dir = temp_dir("shared");
+        let mut ledger = StagingLedger::default();
+        ledger.record(stage(&dir, "first", &[("shared.png", false)]));
+        ledger.record(stage(&dir, "second", &[("shared.png", true)]));
+
+        ledger.unstage("first", false).unwrap();
+        assert!(dir.join("shared.png").exists());
+        ledger.unstage("second", false).unwrap();
+        assert!(!dir.join("shared.png").exists());
+    }
+
+    #[test]
+    fn unstage_refuses_modified_files_unless_forced() {
+        let dir = temp_dir("modified");
+        let mut ledger = StagingLedger::default();
+        ledger.record(stage(&dir, "post", &[]));
+        fs::write(dir.join("post.md"), "edited in the target").unwrap();
+
+        let err = ledger.unstage("post", false).unwrap_err();
+        assert!(err.contains("Modified after staging"));
+        assert!(ledger.contains("post"));
+        assert_eq!(ledger.unstage("post", true).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn ledgers_without_overwritten_flag_still_load() {
+        let ledger: StagingLedger = serde_json::from_str(
+            r#"{"entries": [{
+                "source_path": "post.md",
+                "target_path": "out/post.md",
+                "content_hash": "abc",
+                "staged_at": "2024-01-01T00:00:00Z",
+                "assets": [{"path": "out/cover.png", "content_hash": "def"}]
+            }]}"#,
+        )
+        .unwrap();
+        assert!(!ledger.entries[0].assets[0].overwritten);
+    }
+
+    /// Stage `source` as a bundle named `slug` holding `index.md` and a cover image
+    fn stage_bundle(dir: &Path, source: &str, slug: &str) -> StagedEntry {
+        let bundle_dir = dir.join(slug);
+        fs::create_dir_all(&bundle_dir).unwrap();
+        let target_path = bundle_dir.join("index.md");
+        let content = format!("# {}", slug);
+        fs::write(&target_path, &content).unwrap();
+        let cover = bundle_dir.join("cover.png");
+        fs::write(&cover, "cover").unwrap();
+        let rendered = RenderedWriting {
+            target_path,
+            bundle_dir: Some(bundle_dir),
+            content,
+            warnings: Vec::new(),
+        };
+        StagedEntry::new(source, &rendered, &[cover], &[])
+    }
+
+    #[test]
+    fn restaging_under_a_new_title_removes_the_previous_output() {
+        let dir = temp_dir("retitled");
+        let mut ledger = StagingLedger::default();
+        ledger.record(stage_bundle(&dir, "post.md", "old-title"));
+
+        let stale = ledger.record(stage_bundle(&dir, "post.md", "new-title"));
+        assert_eq!(stale.removed.len(), 2);
+        assert!(stale.kept.is_empty());
+        assert!(!dir.join("old-title").exists());
+        assert!(dir.join("new-title").join("index.md").exists());
+        assert_eq!(ledger.entries.len(), 1);
+
+        // Restaging to the same target deletes nothing
+        let stale = ledger.record(stage_bundle(&dir, "post.md", "new-title"));
+        assert!(stale.removed.is_empty());
+        assert_eq!(ledger.unstage("post.md", false).unwrap().len(), 2);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn restaging_keeps_previous_output_edited_since() {
+        let dir = temp_dir("retitled-edited");
+        let mut ledger = StagingLedger::default();
+        ledger.record(stage_bundle(&dir, "post.md", "old-title"));
+        fs::write(dir.join("old-title").join("index.md"), "edited").unwrap();
+
+        let stale = ledger.record(stage_bundle(&dir, "post.md", "new-title"));
+        assert_eq!(stale.kept, vec![dir.join("old-title").join("index.md").display().to_string()]);
+        assert!(!dir.join("old-title").join("cover.png").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rollback_removes_created_directories() {
+        let dir = temp_dir("rollback");
+        let bundle = dir.join("post");
+        // A directory in the way makes the second rename fail during commit
+        let blocked = dir.join("blocked.md");
+        fs::create_dir_all(blocked.join("inner")).unwrap();
+
+        let mut tx = StagingTransaction::new();
+        tx.write(&bundle.join("images").join("cat.png"), b"cat").unwrap();
+        tx.write(&blocked, b"post").unwrap();
+        assert!(bundle.join("images").is_dir());
+        assert!(tx.commit().is_err());
+
+        assert!(!bundle.exists());
+        let left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(left, vec!["blocked.md"]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn uncommitted_transactions_leave_no_directories() {
+        let dir = temp_dir("uncommitted");
+        let mut tx = StagingTransaction::new();
+        tx.write(&dir.join("post").join("index.md"), b"post").unwrap();
+        drop(tx);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,328 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::asset::Asset;
+use crate::config::Config;
+use crate::embed::{self, EmbedOptions};
+use crate::frontmatter;
+use crate::imaging::ProcessedImage;
+use crate::links::{self, LinkResolver, DEFAULT_PERMALINK};
+
+/// Inputs shared by every transform while staging one writing
+pub struct TransformContext<'a> {
+    pub asset_list: &'a [Asset],
+    /// Images converted during transfer, keyed by source file name
+    pub processed_images: HashMap<String, ProcessedImage>,
+    /// Index of the project's writings for `resolve_wikilinks`
+    pub links: &'a LinkResolver,
+    warnings: RefCell<Vec<String>>,
+}
+
+impl<'a> TransformContext<'a> {
+    pub fn new(
+        asset_list: &'a [Asset],
+        processed_images: HashMap<String, ProcessedImage>,
+        links: &'a LinkResolver,
+    ) -> Self {
+        Self {
+            asset_list,
+            processed_images,
+            links,
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The asset list with converted images renamed to their output file
+    pub fn output_assets(&self) -> Vec<Asset> {
+        self.asset_list
+            .iter()
+            .map(|asset| {
+                let path = Path::new(&asset.asset_path);
+                let processed = path
+                    .file_name()
+                    .and_then(|name| self.processed_images.get(name.to_string_lossy().as_ref()));
+                match processed {
+                    Some(processed) => Asset {
+                        asset_path: path.with_file_name(&processed.file_name).display().to_string(),
+                    },
+                    None => asset.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Report a problem that does not stop staging
+    pub fn warn(&self, message: String) {
+        self.warnings.borrow_mut().push(message);
+    }
+
+    pub fn into_warnings(self) -> Vec<String> {
+        self.warnings.into_inner()
+    }
+}
+
+/// A single staging step. Steps may edit the frontmatter, the body or both.
+pub trait Transform {
+    fn name(&self) -> &'static str;
+
+    fn apply_frontmatter(&self, _frontmatter: &mut Value, _ctx: &TransformContext) {}
+
+    fn apply_body(&self, body: String, _ctx: &TransformContext) -> String {
+        body
+    }
+}
+
+/// One entry of the `pipeline` list in the project TOML.
+///
+/// Either a bare name (`"sanitize_frontmatter"`) or a table with a `name`
+/// and step parameters (`{ name = "trim_tags", prefix = "blog/" }`).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum PipelineStep {
+    Name(String),
+    Configured {
+        name: String,
+        #[serde(flatten)]
+        params: toml::Table,
+    },
+}
+
+impl PipelineStep {
+    pub fn name(&self) -> &str {
+        match self {
+            PipelineStep::Name(name) => name,
+            PipelineStep::Configured { name, .. } => name,
+        }
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        match self {
+            PipelineStep::Name(_) => None,
+            PipelineStep::Configured { params, .. } => params.get(key).and_then(|v| v.as_str()),
+        }
+    }
+}
+
+/// Built-in transforms and what they do, in their default order
+pub const BUILTIN_TRANSFORMS: &[(&str, &str)] = &[
+    ("remove_draft", "Sets the 'draft' flag to false."),
+    ("sanitize_frontmatter", "Removes empty fields from the frontmatter."),
+    ("cover_image", "Adds a cover image from the '<assetPrefix>-header' asset. Param: prefix."),
+    ("hero_image", "Adds a hero image from the '<assetPrefix>-header' asset. Param: prefix."),
+    ("trim_tags", "Strips a prefix from every tag. Param: prefix."),
+    ("image_embeds", "Converts ![[file|alt|300]] embeds to images. Param: prefix."),
+    ("strip_wikilinks", "Converts [[wikilinks]] to plain text."),
+    ("resolve_wikilinks", "Converts [[wikilinks]] to links to published writings. Param: permalink."),
+];
+
+struct RemoveDraft;
+
+impl Transform for RemoveDraft {
+    fn name(&self) -> &'static str {
+        "remove_draft"
+    }
+
+    fn apply_frontmatter(&self, frontmatter: &mut Value, _ctx: &TransformContext) {
+        frontmatter["draft"] = Value::Bool(false);
+    }
+}
+
+struct SanitizeFrontmatter;
+
+impl Transform for SanitizeFrontmatter {
+    fn name(&self) -> &'static str {
+        "sanitize_frontmatter"
+    }
+
+    fn apply_frontmatter(&self, frontmatter: &mut Value, _ctx: &TransformContext) {
+        frontmatter::remove_empty_values(frontmatter);
+    }
+}
+
+struct CoverImage {
+    prefix: String,
+}
+
+impl Transform for CoverImage {
+    fn name(&self) -> &'static str {
+        "cover_image"
+    }
+
+    fn apply_frontmatter(&self, frontmatter: &mut Value, ctx: &TransformContext) {
+        frontmatter::add_cover_image(frontmatter, &self.prefix, &ctx.output_assets());
+    }
+}
+
+struct HeroImage {
+    prefix: String,
+}
+
+impl Transform for HeroImage {
+    fn name(&self) -> &'static str {
+        "hero_image"
+    }
+
+    fn apply_frontmatter(&self, frontmatter: &mut Value, ctx: &TransformContext) {
+        frontmatter::add_hero_image(frontmatter, &self.prefix, &ctx.output_assets());
+    }
+}
+
+struct TrimTags {
+    prefix: String,
+}
+
+impl Transform for TrimTags {
+    fn name(&self) -> &'static str {
+        "trim_tags"
+    }
+
+    fn apply_frontmatter(&self, frontmatter: &mut Value, _ctx: &TransformContext) {
+        frontmatter::strip_tags(frontmatter, &self.prefix);
+    }
+}
+
+struct ImageEmbeds {
+    options: EmbedOptions,
+}
+
+impl Transform for ImageEmbeds {
+    fn name(&self) -> &'static str {
+        "image_embeds"
+    }
+
+    fn apply_body(&self, body: String, ctx: &TransformContext) -> String {
+        let body = embed::rewrite_image_destinations(&body, &ctx.processed_images);
+        embed::convert_embeds(&body, &self.options, &ctx.processed_images, |warning| ctx.warn(warning))
+    }
+}
+
+struct StripWikilinks;
+
+impl Transform for StripWikilinks {
+    fn name(&self) -> &'static str {
+        "strip_wikilinks"
+    }
+
+    fn apply_body(&self, body: String, _ctx: &TransformContext) -> String {
+        links::strip_wikilinks(&body)
+    }
+}
+
+struct ResolveWikilinks {
+    permalink: String,
+}
+
+impl Transform for ResolveWikilinks {
+    fn name(&self) -> &'static str {
+        "resolve_wikilinks"
+    }
+
+    fn apply_body(&self, body: String, ctx: &TransformContext) -> String {
+        ctx.links.resolve(&body, &self.permalink, |warning| ctx.warn(warning))
+    }
+}
+
+/// Look up a built-in transform by name and configure it from the step parameters.
+fn build_transform(step: &PipelineStep, config: &Config) -> Result<Box<dyn Transform>, String> {
+    let prefix_or = |fallback: &Option<String>, field: &str| -> Result<String, String> {
+        step.param("prefix")
+            .map(str::to_string)
+            .or_else(|| fallback.clone())
+            .ok_or_else(|| {
+                format!(
+                    "Transform '{}' needs a 'prefix' parameter or '{}' to be set",
+                    step.name(),
+                    field
+                )
+            })
+    };
+
+    // Bundled posts link to the assets next to them
+    let asset_prefix_or = |fallback: &Option<String>, field: &str| -> Result<String, String> {
+        if config.is_bundle_layout() {
+            Ok(String::new())
+        } else {
+            prefix_or(fallback, field)
+        }
+    };
+
+    let transform: Box<dyn Transform> = match step.name() {
+        "remove_draft" => Box::new(RemoveDraft),
+        "sanitize_frontmatter" => Box::new(SanitizeFrontmatter),
+        "cover_image" => Box::new(CoverImage {
+            prefix: asset_prefix_or(&config.target_asset_prefix, "target_asset_prefix")?,
+        }),
+        "hero_image" => Box::new(HeroImage {
+            prefix: asset_prefix_or(&config.target_hero_image_prefix, "target_hero_image_prefix")?,
+        }),
+        "trim_tags" => Box::new(TrimTags {
+            prefix: prefix_or(&config.tag_prefix, "tag_prefix")?,
+        }),
+        "image_embeds" => Box::new(ImageEmbeds {
+            options: EmbedOptions::new(
+                // Without a prefix, assets are linked from the site root as `/file.png`
+                (!config.is_bundle_layout())
+                    .then(|| prefix_or(&config.target_asset_prefix, "target_asset_prefix").unwrap_or_default()),
+                config.use_mdx_format.unwrap_or(false),
+                config.get_source_asset_dir(),
+            ),
+        }),
+        "strip_wikilinks" => Box::new(StripWikilinks),
+        "resolve_wikilinks" => Box::new(ResolveWikilinks {
+            permalink: step.param("permalink").unwrap_or(DEFAULT_PERMALINK).to_string(),
+        }),
+        other => {
+            let known = BUILTIN_TRANSFORMS.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            return Err(format!(
+                "Unknown transform '{}'. Available: {}",
+                other,
+                known.join(", ")
+            ));
+        }
+    };
+    Ok(transform)
+}
+
+/// Steps implied by the legacy boolean toggles, used when no `pipeline` is set
+fn legacy_steps(config: &Config) -> Vec<PipelineStep> {
+    let toggles = [
+        ("remove_draft", config.remove_draft_on_stage),
+        ("sanitize_frontmatter", config.sanitize_frontmatter),
+        ("cover_image", config.auto_add_cover_img),
+        ("hero_image", config.auto_add_hero_img),
+        ("trim_tags", config.trim_tags),
+        ("image_embeds", Some(true)),
+        ("strip_wikilinks", config.remove_wikilinks),
+    ];
+    toggles
+        .into_iter()
+        .filter(|(_, enabled)| enabled.unwrap_or(false))
+        .map(|(name, _)| PipelineStep::Name(name.to_string()))
+        .collect()
+}
+
+/// Build the ordered transform pipeline for a project.
+pub fn build_pipeline(config: &Config) -> Result<Vec<Box<dyn Transform>>, String> {
+    let steps = match &config.pipeline {
+        Some(steps) => steps.clone(),
+        None => legacy_steps(config),
+    };
+    steps.iter().map(|step| build_transform(step, config)).collect()
+}
+
+/// Run every transform over the frontmatter and body, in pipeline order.
+pub fn apply_pipeline(
+    pipeline: &[Box<dyn Transform>],
+    frontmatter: &mut Value,
+    body: String,
+    ctx: &TransformContext,
+) -> String {
+    pipeline.iter().fold(body, |body, transform| {
+        transform.apply_frontmatter(frontmatter, ctx);
+        transform.apply_body(body, ctx)
+    })
+}
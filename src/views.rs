@@ -159,10 +159,16 @@ fn draw_writings_list(f: &mut Frame, dashboard: &Dashboard, list_state: &mut Lis
         vec![ListItem::new(Line::from(Span::styled("No writings found", theme.muted_style())))]
     } else {
         dashboard.writings.iter().map(|writing| {
-            let status_tag = if writing.is_draft { " DRAFT " } else { " PUB " };
-            let status_bg = if writing.is_draft { theme.warning } else { theme.success };
+            let (status_tag, status_bg) = if writing.is_broken() {
+                (" ERR ", theme.danger)
+            } else if writing.is_draft {
+                (" DRAFT ", theme.warning)
+            } else {
+                (" PUB ", theme.success)
+            };
+            let problem_tag = if writing.diagnostic.is_some() && !writing.is_broken() { " !" } else { "" };
 
-            let staged_tag = if dashboard.staged_writings.contains(&writing.path) {
+            let staged_tag = if dashboard.is_staged(&writing.path) {
                 if dashboard.auto_stage_enabled { " [AUTO]" } else { " [STAGED]" }
             } else {
                 ""
@@ -178,6 +184,7 @@ fn draw_writings_list(f: &mut Frame, dashboard: &Dashboard, list_state: &mut Lis
                     Style::default().fg(theme.highlight_bg).bg(status_bg).add_modifier(Modifier::BOLD),
                 ),
                 Span::raw(&writing.title),
+                Span::styled(problem_tag, theme.warning_style().add_modifier(Modifier::BOLD)),
                 Span::styled(staged_tag, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
                 Span::styled(format!(" {:>10}", date_str), Style::default().fg(theme.muted)),
             ]);
@@ -186,7 +193,7 @@ fn draw_writings_list(f: &mut Frame, dashboard: &Dashboard, list_state: &mut Lis
     };
 
     let writings_count = dashboard.writings.len();
-    let staged_count = dashboard.staged_writings.len();
+    let staged_count = dashboard.staged_count();
     let auto_stage_status = if dashboard.auto_stage_enabled { "ON" } else { "OFF" };
     let title = if staged_count > 0 {
         format!("Writings ({} total, {} staged, auto-stage: {})", writings_count, staged_count, auto_stage_status)
@@ -203,9 +210,14 @@ fn draw_writings_list(f: &mut Frame, dashboard: &Dashboard, list_state: &mut Lis
 
 fn draw_writing_details(f: &mut Frame, dashboard: &Dashboard, area: Rect, theme: &Theme) {
     let details = if let Some(writing) = dashboard.writings.get(dashboard.selected_writings_index) {
-        let status = if writing.is_draft { "Draft" } else { "Published" };
-        let status_color = if writing.is_draft { theme.warning } else { theme.success };
-        let is_staged = dashboard.staged_writings.contains(&writing.path);
+        let (status, status_color) = if writing.is_broken() {
+            ("Unreadable frontmatter", theme.danger)
+        } else if writing.is_draft {
+            ("Draft", theme.warning)
+        } else {
+            ("Published", theme.success)
+        };
+        let is_staged = dashboard.is_staged(&writing.path);
 
         let publish_date_str = writing.publish_date
             .map(|d: chrono::NaiveDate| d.format("%Y-%m-%d").to_string())
@@ -241,6 +253,20 @@ fn draw_writing_details(f: &mut Frame, dashboard: &Dashboard, area: Rect, theme:
             lines.push(Line::from(Span::styled(staging_info, theme.success_style())));
         }
 
+        if let Some(diagnostic) = &writing.diagnostic {
+            let (label, style) = if diagnostic.skipped {
+                ("Error: ", theme.danger_style())
+            } else {
+                ("Warning: ", theme.warning_style())
+            };
+            let location = diagnostic.line.map(|l| format!("line {}: ", l)).unwrap_or_default();
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::styled(label, style.add_modifier(Modifier::BOLD)),
+                Span::styled(format!("{}{}", location, diagnostic.message), style),
+            ]));
+        }
+
         Paragraph::new(Text::from(lines))
             .block(Block::default().title("Writing Details").borders(Borders::ALL).border_style(theme.border_style()))
             .wrap(Wrap { trim: true })
@@ -413,7 +439,7 @@ fn draw_help_popup(f: &mut Frame, theme: &Theme) {
         Line::from("Auto-staging:"),
         Line::from("  When enabled, staged writings are automatically"),
         Line::from("  re-staged when the source file is modified."),
-        Line::from("  File watching continues until you exit LazyDraft."),
+        Line::from("  Staged writings are remembered between sessions."),
         Line::from(""),
         Line::from("General:"),
         Line::from("  h/F1          Toggle this help"),
@@ -424,6 +450,7 @@ fn draw_help_popup(f: &mut Frame, theme: &Theme) {
         Line::from("  ⚠             Unconfigured project"),
         Line::from("  DRAFT         Draft writing"),
         Line::from("  PUB           Published writing"),
+        Line::from("  ERR           Frontmatter could not be read, see details"),
         Line::from("  [AUTO]        Staged with auto-staging enabled"),
         Line::from("  [STAGED]      Staged without auto-staging"),
         Line::from(""),
@@ -455,7 +482,7 @@ fn draw_revert_confirm_popup(f: &mut Frame, theme: &Theme) {
     let area = centered_rect(50, 25, f.size());
     f.render_widget(Clear, area);
 
-    let popup = Paragraph::new("Revert staging for this writing?\n\nThis will remove it from the staged list.\n\ny: Yes | n: No")
+    let popup = Paragraph::new("Revert staging for this writing?\n\nThis will delete the staged file and its copied assets.\n\ny: Yes | n: No")
         .style(Style::default().fg(theme.text))
         .alignment(Alignment::Center)
         .block(Block::default().title("Revert Staging").borders(Borders::ALL).border_style(theme.danger_style()))
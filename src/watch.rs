@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config as NotifyConfig, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+use crate::asset::get_asset_list_of_writing;
+use crate::config::Config;
+use crate::discovery::Discovery;
+use crate::naming;
+use crate::writing::{load_writing, scan_writings, ParseDiagnostic, Writing};
+
+/// A change to the vault, derived from raw file events
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceChange {
+    /// A note was created or edited
+    Changed(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+    Removed(PathBuf),
+    /// A file in `source_asset_dir` was created, edited or removed
+    AssetChanged(PathBuf),
+}
+
+/// Writings of a project kept in memory and updated note by note, so file
+/// events don't rescan the whole vault
+pub struct WritingIndex {
+    writings: HashMap<PathBuf, Writing>,
+    /// Asset files each writing uses, looked up on demand and dropped when
+    /// the note changes. `None` while the lookup fails.
+    assets: HashMap<PathBuf, Option<Vec<PathBuf>>>,
+}
+
+impl WritingIndex {
+    pub fn build(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let writings = scan_writings(config)?
+            .writings
+            .into_iter()
+            .map(|w| (PathBuf::from(&w.path), w))
+            .collect();
+        Ok(WritingIndex {
+            writings,
+            assets: HashMap::new(),
+        })
+    }
+
+    pub fn writings(&self) -> impl Iterator<Item = &Writing> {
+        self.writings.values()
+    }
+
+    /// Re-read one note. A note that can no longer be parsed leaves the index.
+    pub fn refresh(&mut self, config: &Config, path: &Path) -> Result<Writing, ParseDiagnostic> {
+        self.assets.remove(path);
+        match load_writing(config, path) {
+            Ok(writing) => {
+                self.writings.insert(path.to_path_buf(), writing);
+                self.dedupe_slugs();
+                Ok(self.writings[path].clone())
+            }
+            Err(diagnostic) => {
+                self.remove(path);
+                Err(diagnostic)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<Writing> {
+        self.assets.remove(path);
+        let removed = self.writings.remove(path);
+        if removed.is_some() {
+            self.dedupe_slugs();
+        }
+        removed
+    }
+
+    /// Writings matching `filter` that use the asset at `asset`.
+    ///
+    /// Asset lists are kept between calls. A file no list mentions yet, such
+    /// as a newly added image, may belong to any note, so every note matching
+    /// `filter` is looked up again.
+    pub fn users_of(&mut self, config: &Config, asset: &Path, filter: impl Fn(&Writing) -> bool) -> Vec<Writing> {
+        let known = self.assets.values().flatten().flatten().any(|a| a == asset);
+        let mut users = Vec::new();
+        for (path, writing) in self.writings.iter().filter(|(_, w)| filter(w)) {
+            let cached = known && self.assets.get(path).is_some_and(Option::is_some);
+            if !cached {
+                let lookup = get_asset_list_of_writing(writing, config)
+                    .ok()
+                    .map(|list| list.into_iter().map(|a| PathBuf::from(a.asset_path)).collect());
+                self.assets.insert(path.clone(), lookup);
+            }
+            if self.assets[path].iter().flatten().any(|a| a == asset) {
+                users.push(writing.clone());
+            }
+        }
+        users
+    }
+
+    fn dedupe_slugs(&mut self) {
+        let mut writings: Vec<Writing> = self.writings.drain().map(|(_, w)| w).collect();
+        naming::dedupe_slugs(&mut writings);
+        self.writings = writings
+            .into_iter()
+            .map(|w| (PathBuf::from(&w.path), w))
+            .collect();
+    }
+}
+
+/// Turns raw watcher events into `SourceChange`s for one project
+pub struct ChangeClassifier {
+    discovery: Discovery,
+    asset_dir: Option<PathBuf>,
+}
+
+impl ChangeClassifier {
+    pub fn new(config: &Config) -> Result<Self, String> {
+        Ok(ChangeClassifier {
+            discovery: Discovery::new(config)?,
+            asset_dir: config
+                .get_source_asset_dir()
+                .filter(|d| !d.is_empty())
+                .map(PathBuf::from),
+        })
+    }
+
+    pub fn classify(&self, event: &Event) -> Vec<SourceChange> {
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let (from, to) = (&event.paths[0], &event.paths[1]);
+                match (self.discovery.is_writing(from), self.discovery.is_writing(to)) {
+                    (true, true) => vec![SourceChange::Renamed {
+                        from: from.clone(),
+                        to: to.clone(),
+                    }],
+                    // Renamed into or out of what counts as a writing
+                    (true, false) => vec![SourceChange::Removed(from.clone())],
+                    (false, true) => vec![SourceChange::Changed(to.clone())],
+                    (false, false) => self.asset_changes(&event.paths),
+                }
+            }
+            // Paired halves of a rename also arrive as a single `Both` event
+            EventKind::Modify(ModifyKind::Name(RenameMode::From | RenameMode::To))
+                if event.attrs.tracker().is_some() =>
+            {
+                Vec::new()
+            }
+            // Unpaired renames: judge each path by whether it still exists
+            EventKind::Modify(ModifyKind::Name(_)) => event
+                .paths
+                .iter()
+                .filter_map(|path| self.note_change(path, !path.exists()))
+                .chain(self.asset_changes(&event.paths))
+                .collect(),
+            EventKind::Create(_) | EventKind::Modify(_) => event
+                .paths
+                .iter()
+                .filter_map(|path| self.note_change(path, false))
+                .chain(self.asset_changes(&event.paths))
+                .collect(),
+            EventKind::Remove(_) => event
+                .paths
+                .iter()
+                .filter_map(|path| self.note_change(path, true))
+                .chain(self.asset_changes(&event.paths))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn note_change(&self, path: &Path, removed: bool) -> Option<SourceChange> {
+        if !self.discovery.is_writing(path) {
+            return None;
+        }
+        Some(if removed {
+            SourceChange::Removed(path.to_path_buf())
+        } else {
+            SourceChange::Changed(path.to_path_buf())
+        })
+    }
+
+    fn asset_changes(&self, paths: &[PathBuf]) -> Vec<SourceChange> {
+        let Some(asset_dir) = &self.asset_dir else {
+            return Vec::new();
+        };
+        paths
+            .iter()
+            .filter(|path| path.starts_with(asset_dir) && !self.discovery.is_writing(path))
+            .filter(|path| !path.is_dir() && !is_hidden_file(path))
+            .map(|path| SourceChange::AssetChanged(path.clone()))
+            .collect()
+    }
+
+    /// Directories to watch: `source_dir`, plus `source_asset_dir` when it
+    /// lives outside of it
+    pub fn watch_roots(config: &Config) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(source_dir) = config.get_source_dir() {
+            roots.push(PathBuf::from(source_dir));
+        }
+        if let Some(asset_dir) = config.get_source_asset_dir().filter(|d| !d.is_empty()) {
+            let asset_dir = PathBuf::from(asset_dir);
+            if !roots.iter().any(|root| asset_dir.starts_with(root)) {
+                roots.push(asset_dir);
+            }
+        }
+        roots
+    }
+}
+
+/// Editor swap files and `.DS_Store` next to the images
+fn is_hidden_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Quiet period used when a project sets no `debounce_ms`
+pub const DEFAULT_DEBOUNCE_MS: u64 = 1000;
+
+impl SourceChange {
+    /// The path changes are coalesced by; a rename is keyed by its new name
+    fn key(&self) -> &Path {
+        match self {
+            SourceChange::Changed(path)
+            | SourceChange::Removed(path)
+            | SourceChange::AssetChanged(path) => path,
+            SourceChange::Renamed { to, .. } => to,
+        }
+    }
+}
+
+/// Holds changes back until their path has been quiet for a while, merging
+/// the bursts of events editors fire for a single save
+pub struct Debouncer {
+    quiet_period: Duration,
+    pending: HashMap<PathBuf, (SourceChange, Instant)>,
+}
+
+impl Debouncer {
+    pub fn new(quiet_period: Duration) -> Self {
+        Debouncer {
+            quiet_period,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn for_config(config: &Config) -> Self {
+        Self::new(Duration::from_millis(config.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS)))
+    }
+
+    pub fn push(&mut self, change: SourceChange) {
+        let now = Instant::now();
+        let change = match change {
+            SourceChange::Renamed { from, to } => match self.pending.remove(&from).map(|(c, _)| c) {
+                // A note renamed again before settling keeps its first name
+                Some(SourceChange::Renamed { from: first, .. }) if first == to => SourceChange::Changed(to),
+                Some(SourceChange::Renamed { from: first, .. }) => SourceChange::Renamed { from: first, to },
+                // Edits under the old name are part of the rename
+                _ => SourceChange::Renamed { from, to },
+            },
+            change => change,
+        };
+        let key = change.key().to_path_buf();
+        let merged = match (self.pending.remove(&key).map(|(c, _)| c), change) {
+            // Content edits after a rename are staged with it
+            (Some(renamed @ SourceChange::Renamed { .. }), SourceChange::Changed(_)) => renamed,
+            // The renamed note is gone again: clean up under the old name
+            (Some(SourceChange::Renamed { from, .. }), SourceChange::Removed(_)) => {
+                SourceChange::Removed(from)
+            }
+            // Anything else, such as an atomic save's remove then create, ends in its last state
+            (_, latest) => latest,
+        };
+        self.pending.insert(merged.key().to_path_buf(), (merged, now));
+    }
+
+    /// Time until the next change is due, `None` when nothing is pending
+    pub fn next_due(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.pending
+            .values()
+            .map(|(_, last)| (*last + self.quiet_period).saturating_duration_since(now))
+            .min()
+    }
+
+    /// Changes whose path has been quiet for the whole period
+    pub fn take_ready(&mut self) -> Vec<SourceChange> {
+        let now = Instant::now();
+        let ready: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, last))| now.duration_since(*last) >= self.quiet_period)
+            .map(|(path, _)| path.clone())
+            .collect();
+        let mut changes: Vec<SourceChange> = ready
+            .into_iter()
+            .filter_map(|path| self.pending.remove(&path).map(|(change, _)| change))
+            .collect();
+        changes.sort_by(|a, b| a.key().cmp(b.key()));
+        changes
+    }
+
+    /// Every pending change, quiet or not, for a last stage before shutdown
+    pub fn drain(&mut self) -> Vec<SourceChange> {
+        let mut changes: Vec<SourceChange> = self.pending.drain().map(|(_, (change, _))| change).collect();
+        changes.sort_by(|a, b| a.key().cmp(b.key()));
+        changes
+    }
+}
+
+/// How often the watch loop checks for a shutdown request while idle
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A flag raised by Ctrl-C or SIGTERM, so work in progress can finish before
+/// exiting. A second signal while shutting down exits right away.
+pub fn shutdown_flag() -> io::Result<Arc<AtomicBool>> {
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register_conditional_shutdown(signal, 1, Arc::clone(&stop))?;
+        signal_hook::flag::register(signal, Arc::clone(&stop))?;
+    }
+    Ok(stop)
+}
+
+/// Watches a project's source and asset directories and hands out changes
+/// once they have settled
+pub struct SourceWatcher {
+    // Events stop arriving once the watcher is dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    classifier: ChangeClassifier,
+    debouncer: Debouncer,
+    roots: Vec<PathBuf>,
+}
+
+impl SourceWatcher {
+    pub fn new(config: &Config) -> Result<Self, String> {
+        let classifier = ChangeClassifier::new(config)?;
+        let (tx, events) = channel();
+        let mut watcher = RecommendedWatcher::new(
+            tx,
+            NotifyConfig::default().with_poll_interval(Duration::from_secs(2)),
+        )
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+        let roots = ChangeClassifier::watch_roots(config);
+        for root in &roots {
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+        }
+        Ok(SourceWatcher {
+            _watcher: watcher,
+            events,
+            classifier,
+            debouncer: Debouncer::for_config(config),
+            roots,
+        })
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Pass each batch of settled changes, or a failed event, to `handle`
+    /// until `stop` is raised. Changes still in their quiet period are left
+    /// for `drain`.
+    pub fn run(
+        &mut self,
+        stop: &AtomicBool,
+        mut handle: impl FnMut(Result<Vec<SourceChange>, notify::Error>),
+    ) -> Result<(), String> {
+        while !stop.load(Ordering::Relaxed) {
+            // Sleep until the next settled change is due or a new event arrives,
+            // waking regularly to notice a shutdown request
+            let timeout = self
+                .debouncer
+                .next_due()
+                .map_or(STOP_POLL_INTERVAL, |due| due.min(STOP_POLL_INTERVAL));
+            match self.events.recv_timeout(timeout) {
+                Ok(Ok(event)) => {
+                    for change in self.classifier.classify(&event) {
+                        self.debouncer.push(change);
+                    }
+                }
+                Ok(Err(e)) => handle(Err(e)),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(e) => return Err(format!("Watcher stopped: {}", e)),
+            }
+            let changes = self.debouncer.take_ready();
+            if !changes.is_empty() {
+                handle(Ok(changes));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every change still waiting for its quiet period
+    pub fn drain(&mut self) -> Vec<SourceChange> {
+        self.debouncer.drain()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(format!("/vault/{}.md", name))
+    }
+
+    fn renamed(from: &str, to: &str) -> SourceChange {
+        SourceChange::Renamed {
+            from: path(from),
+            to: path(to),
+        }
+    }
+
+    fn settle(changes: &[SourceChange]) -> Vec<SourceChange> {
+        let mut debouncer = Debouncer::new(Duration::ZERO);
+        for change in changes {
+            debouncer.push(change.clone());
+        }
+        debouncer.take_ready()
+    }
+
+    #[test]
+    fn edits_before_a_rename_are_folded_into_it() {
+        let changes = settle(&[SourceChange::Changed(path("a")), renamed("a", "b")]);
+        assert_eq!(changes, vec![renamed("a", "b")]);
+    }
+
+    #[test]
+    fn chained_renames_keep_the_first_name() {
+        assert_eq!(settle(&[renamed("a", "b"), renamed("b", "c")]), vec![renamed("a", "c")]);
+        assert_eq!(
+            settle(&[renamed("a", "b"), renamed("b", "a")]),
+            vec![SourceChange::Changed(path("a"))]
+        );
+    }
+
+    #[test]
+    fn edits_after_a_rename_are_staged_with_it() {
+        let changes = settle(&[renamed("a", "b"), SourceChange::Changed(path("b"))]);
+        assert_eq!(changes, vec![renamed("a", "b")]);
+    }
+
+    #[test]
+    fn removing_a_renamed_note_removes_the_old_name() {
+        let changes = settle(&[renamed("a", "b"), SourceChange::Removed(path("b"))]);
+        assert_eq!(changes, vec![SourceChange::Removed(path("a"))]);
+    }
+
+    #[test]
+    fn atomic_saves_end_in_their_last_state() {
+        let changes = settle(&[SourceChange::Removed(path("a")), SourceChange::Changed(path("a"))]);
+        assert_eq!(changes, vec![SourceChange::Changed(path("a"))]);
+    }
+
+    #[test]
+    fn finds_asset_users_and_notices_edited_notes() {
+        let dir = std::env::temp_dir().join(format!("lazydraft-watch-assets-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let assets = dir.join("assets");
+        fs::create_dir_all(&assets).unwrap();
+        for name in ["cat.png", "dog.png"] {
+            fs::write(assets.join(name), name).unwrap();
+        }
+        let note = dir.join("Post.md");
+        fs::write(&note, "---\ntitle: Post\n---\n\n![[cat.png]]\n").unwrap();
+        let config: Config = toml::from_str(&format!(
+            "source_dir = {:?}\nsource_asset_dir = {:?}\nasset_discovery = \"references\"",
+            dir.display().to_string(),
+            assets.display().to_string()
+        ))
+        .unwrap();
+
+        let mut index = WritingIndex::build(&config).unwrap();
+        let users = |index: &mut WritingIndex, name: &str| -> Vec<String> {
+            index
+                .users_of(&config, &assets.join(name), |_| true)
+                .into_iter()
+                .map(|w| w.title)
+                .collect()
+        };
+        assert_eq!(users(&mut index, "cat.png"), vec!["Post"]);
+        assert!(users(&mut index, "dog.png").is_empty());
+
+        fs::write(&note, "---\ntitle: Post\n---\n\n![[dog.png]]\n").unwrap();
+        index.refresh(&config, &note).unwrap();
+        assert!(users(&mut index, "cat.png").is_empty());
+        assert_eq!(users(&mut index, "dog.png"), vec!["Post"]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn changes_wait_for_the_quiet_period() {
+        let mut debouncer = Debouncer::new(Duration::from_secs(60));
+        debouncer.push(SourceChange::Changed(path("a")));
+        assert!(debouncer.take_ready().is_empty());
+        assert!(debouncer.next_due().is_some());
+        assert_eq!(debouncer.drain(), vec![SourceChange::Changed(path("a"))]);
+        assert_eq!(debouncer.next_due(), None);
+    }
+}
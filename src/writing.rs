@@ -1,45 +1,89 @@
 use std::{
-    fs::{self, File},
-    io::{self, Write},
-    path::Path,
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
 };
 
 use crate::{
     asset::Asset,
     cli,
     config::Config,
+    discovery::Discovery,
+    document::Document,
     frontmatter,
+    imaging::plan_images,
+    links::LinkResolver,
+    naming,
+    preset::Preset,
+    staging::{StagingTransaction, WriteOutcome},
+    transform::{self, TransformContext},
 };
 use chrono::NaiveDate;
 use colored::*;
 use dialoguer::Select;
 use itertools::Itertools;
 
-use walkdir::WalkDir;
-
 #[derive(Debug, Clone)]
 pub struct Writing {
     pub path: String,
     pub title: String,
     pub is_draft: bool,
     pub publish_date: Option<NaiveDate>,
+    /// Unique URL slug, shared by the target file name and resolved links
+    pub slug: String,
+    /// The slug this note wanted before it was made unique
+    pub slug_collision: Option<String>,
+    /// Problem found while reading the note, shown by `status` and the dashboard
+    pub diagnostic: Option<ParseDiagnostic>,
 }
 
 impl Writing {
-    fn new(path: String, title: String, is_draft: bool, publish_date: &str) -> Self {
-        let date = match NaiveDate::parse_from_str(publish_date, "%Y-%m-%d") {
-            Ok(date) => Some(date),
-            Err(_) => None,
-        };
+    fn new(path: String, title: String, is_draft: bool, publish_date: &str, slug: Option<&str>) -> Self {
+        let date = NaiveDate::parse_from_str(publish_date, "%Y-%m-%d").ok();
+        let slug = naming::writing_slug(slug, &title, &path);
         Writing {
             path,
             title,
             is_draft,
             publish_date: date,
+            slug,
+            slug_collision: None,
+            diagnostic: None,
+        }
+    }
+
+    /// Placeholder for a note whose frontmatter could not be read
+    pub fn broken(diagnostic: ParseDiagnostic) -> Self {
+        let title = Path::new(&diagnostic.path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Writing {
+            path: diagnostic.path.clone(),
+            slug: naming::slug(&title),
+            title,
+            is_draft: false,
+            publish_date: None,
+            slug_collision: None,
+            diagnostic: Some(diagnostic),
         }
     }
+
+    /// The note is listed for its diagnostic only and cannot be staged
+    pub fn is_broken(&self) -> bool {
+        self.diagnostic.as_ref().is_some_and(|d| d.skipped)
+    }
 }
 
+/// Slug of the source file name, so notes can be found by it even when
+/// their URL slug comes from the title
+fn file_slug(writing: &Writing) -> String {
+    let stem = Path::new(&writing.path).file_stem().unwrap_or_default();
+    naming::slug(&stem.to_string_lossy())
+}
+
+
 pub fn print_writing_list(writings: Vec<Writing>) {
     cli::section("Writings");
     println!(
@@ -82,7 +126,7 @@ pub fn print_writing_list(writings: Vec<Writing>) {
     );
 }
 
-pub fn select_draft_writing_from_list(writings: &Vec<Writing>) -> Option<&Writing> {
+pub fn select_draft_writing_from_list(writings: &[Writing]) -> Option<&Writing> {
     let draft_writings: Vec<&Writing> = writings
         .iter()
         .filter(|&writing| writing.is_draft)
@@ -98,140 +142,293 @@ pub fn select_draft_writing_from_list(writings: &Vec<Writing>) -> Option<&Writin
         .map(|writing| writing.title.clone())
         .collect();
 
-    let selection = match Select::new()
+    Select::new()
         .with_prompt("Select a draft writing")
         .items(&items)
         .interact()
-    {
-        Ok(index) => Some(index),
-        Err(_) => None,
-    };
-    match selection {
-        Some(index) => Some(draft_writings[index]),
-        None => None,
+        .ok()
+        .map(|index| draft_writings[index])
+}
+
+/// Resolve a single writing by path, title or slug without prompting.
+///
+/// Paths win over titles and titles win over slugs, so an exact path always
+/// resolves even when another writing shares its title.
+pub fn find_writing<'a>(writings: &'a [Writing], query: &str) -> Result<&'a Writing, String> {
+    let query_path = fs::canonicalize(query).ok();
+    let by_path: Vec<&Writing> = writings
+        .iter()
+        .filter(|w| {
+            w.path == query
+                || query_path
+                    .as_ref()
+                    .is_some_and(|q| fs::canonicalize(&w.path).ok().as_ref() == Some(q))
+        })
+        .collect();
+    let by_title: Vec<&Writing> = writings
+        .iter()
+        .filter(|w| w.title.eq_ignore_ascii_case(query))
+        .collect();
+    let query_slug = naming::slug(query);
+    let by_slug: Vec<&Writing> = writings
+        .iter()
+        .filter(|w| !query_slug.is_empty() && (w.slug == query_slug || file_slug(w) == query_slug))
+        .collect();
+
+    let matches = [by_path, by_title, by_slug]
+        .into_iter()
+        .find(|m| !m.is_empty())
+        .ok_or_else(|| format!("No writing matches '{}'", query))?;
+
+    if matches.len() > 1 {
+        let candidates = matches
+            .iter()
+            .map(|w| format!("{} ({})", w.title, w.path))
+            .join(", ");
+        return Err(format!(
+            "'{}' is ambiguous, it matches {} writings: {}",
+            query,
+            matches.len(),
+            candidates
+        ));
     }
+    Ok(matches[0])
 }
 
-pub fn update_writing_content_and_transfer(
+/// The generated target file for a writing, before it is written to disk
+pub struct RenderedWriting {
+    pub target_path: PathBuf,
+    /// The post's own directory in the bundle layout
+    pub bundle_dir: Option<PathBuf>,
+    pub content: String,
+    pub warnings: Vec<String>,
+}
+
+/// Run the staging transforms in memory and compute the target file path.
+pub fn render_writing(
     config: &Config,
     writing: &Writing,
-    asset_list: &Vec<Asset>,
-) -> io::Result<()> {
-    if let Ok((frontmatter, markdown_content)) = read_markdown_file(&writing.path) {
-        let mut modifiable_frontmatter = frontmatter.clone();
+    asset_list: &[Asset],
+    links: &LinkResolver,
+) -> io::Result<RenderedWriting> {
+    let source = fs::read_to_string(&writing.path)?;
+    let document = Document::parse(&source)
+        .map_err(|e| io::Error::other(format!("Cannot read {}: {}", writing.path, e)))?;
+    let key_map = config.frontmatter_map();
+    let mut modifiable_frontmatter = document.frontmatter.clone();
+    frontmatter::normalize_source_keys(&mut modifiable_frontmatter, &key_map);
+    let markdown_content = document.body.clone();
 
-        if config.remove_draft_on_stage.unwrap_or(false) {
-            modifiable_frontmatter["draft"] = serde_yaml::to_value(false).expect("disable draft");
-        }
-        if config.sanitize_frontmatter.unwrap_or(false) {
-            frontmatter::remove_empty_values(&mut modifiable_frontmatter);
-        }
-        if config.auto_add_cover_img.unwrap_or(false) {
-            frontmatter::add_cover_image(&mut modifiable_frontmatter, config, &asset_list);
-        }
-        if config.auto_add_hero_img.unwrap_or(false) {
-            frontmatter::add_hero_image(&mut modifiable_frontmatter, config, &asset_list);
-        }
-        if config.trim_tags.unwrap_or(false) {
-            frontmatter::strip_tags(
-                &mut modifiable_frontmatter,
-                &config.tag_prefix.as_deref().unwrap_or(""),
-            );
-        }
-        let mut updated_content = frontmatter::change_image_formats(markdown_content, config);
-        if config.remove_wikilinks.unwrap_or(false) {
-            updated_content = frontmatter::strip_wikilinks(updated_content.to_string());
-        }
+    let pipeline = transform::build_pipeline(config).map_err(io::Error::other)?;
+    let ctx = TransformContext::new(asset_list, plan_images(config, asset_list)?, links);
+    let updated_content =
+        transform::apply_pipeline(&pipeline, &mut modifiable_frontmatter, markdown_content, &ctx);
+    let mut warnings = ctx.into_warnings();
 
-        let writing_name = frontmatter::create_writing_name(&mut modifiable_frontmatter, config, &writing.path);
-
-        let target_dir = config
-            .get_target_dir()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "target_dir should be set"))?;
-        // Determine file extension based on config
-        let file_name = if config.use_mdx_format.unwrap_or(false) {
-            // Change extension to .mdx
-            let stem = Path::new(&writing_name)
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy();
-            format!("{}.mdx", stem)
-        } else {
-            writing_name.clone()
-        };
-        let target_file_name = Path::new(&target_dir).join(file_name);
-        
-        // Ensure target directory exists
-        if let Some(parent_dir) = target_file_name.parent() {
-            fs::create_dir_all(parent_dir)?;
-        }
-        
-        let merged_content = format!(
-            "---\n{}\n{}",
-            serde_yaml::to_string(&modifiable_frontmatter)
-                .expect("frontmatter format should be correct after modification"),
-            updated_content
-        );
-        let mut new_file = File::create(target_file_name)?;
-        new_file.write_all(merged_content.as_bytes())
-    } else {
-        Err(io::Error::new(io::ErrorKind::Other, "Cannot read writing."))
+    let file_name = frontmatter::create_writing_name(&modifiable_frontmatter, config, writing);
+    let preset = config.preset.as_deref().and_then(|name| Preset::parse(name).ok());
+    warnings.extend(preset.and_then(|preset| preset.check_file_name(&file_name)));
+    if let Some(wanted) = &writing.slug_collision {
+        warnings.push(format!(
+            "Slug '{}' is used by another note, staged as '{}'",
+            wanted, writing.slug
+        ));
     }
-}
 
+    let target_dir = config
+        .get_target_dir()
+        .ok_or_else(|| io::Error::other("target_dir should be set"))?;
+    // Bundles are a slug directory holding index.md and the post's assets
+    let (target_path, bundle_dir) = if config.is_bundle_layout() {
+        let stem = Path::new(&file_name).file_stem().unwrap_or_default().to_string_lossy();
+        let extension = Path::new(&file_name).extension().unwrap_or_default().to_string_lossy();
+        let bundle_dir = Path::new(&target_dir).join(naming::slug(&stem));
+        (bundle_dir.join(format!("index.{}", extension)), Some(bundle_dir))
+    } else {
+        (Path::new(&target_dir).join(file_name), None)
+    };
+
+    frontmatter::apply_target_keys(&mut modifiable_frontmatter, &key_map);
+    let content = document.render_as(
+        config.frontmatter_format().unwrap_or(document.format),
+        &modifiable_frontmatter,
+        &updated_content,
+        &frontmatter::key_renames(&key_map),
+    );
+    Ok(RenderedWriting {
+        target_path,
+        bundle_dir,
+        content,
+        warnings,
+    })
+}
 
+/// Render a writing and queue it for the target, skipping the write when the
+/// target already holds identical content.
+pub fn update_writing_content_and_transfer(
+    config: &Config,
+    writing: &Writing,
+    asset_list: &[Asset],
+    links: &LinkResolver,
+    tx: &mut StagingTransaction,
+) -> io::Result<(RenderedWriting, WriteOutcome)> {
+    let rendered = render_writing(config, writing, asset_list, links)?;
+    let outcome = tx.write(&rendered.target_path, rendered.content.as_bytes())?;
+    Ok((rendered, outcome))
+}
 
+/// Read a note and split it into frontmatter and body. YAML (`---`), TOML
+/// (`+++`) and JSON frontmatter are supported.
+///
+/// Source-side key aliases from the project's frontmatter map are renamed to
+/// the keys LazyDraft uses, so callers can always read `title`, `draft`, etc.
 pub fn read_markdown_file(
     file_path: &String,
+    config: &Config,
 ) -> Result<(serde_yaml::Value, String), Box<dyn std::error::Error>> {
     let markdown_content = fs::read_to_string(file_path)?;
-    let mut lines = markdown_content.lines().peekable();
-
-    let frontmatter: serde_yaml::Value = match lines.next() {
-        Some("---") => {
-            let yaml_lines: Vec<&str> = lines
-                .peeking_take_while(|line| !line.starts_with("---"))
-                .collect();
-            serde_yaml::from_str(&yaml_lines.join("\n"))?
+    let document = Document::parse(&markdown_content)?;
+    let mut frontmatter = document.frontmatter;
+    frontmatter::normalize_source_keys(&mut frontmatter, &config.frontmatter_map());
+    Ok((frontmatter, document.body))
+}
+
+/// A problem found while reading a note for the writing list
+#[derive(Debug, Clone)]
+pub struct ParseDiagnostic {
+    pub path: String,
+    /// 1-based line in the note, when known
+    pub line: Option<usize>,
+    pub message: String,
+    /// The note could not be read and is missing from the writing list
+    pub skipped: bool,
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path, line, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
         }
-        _ => serde_yaml::Value::Null,
-    };
-    let markdown_content = lines.collect_vec().join("\n");
-    Ok((frontmatter, markdown_content))
+    }
+}
+
+/// Writings of a project together with the problems found reading them
+pub struct WritingScan {
+    pub writings: Vec<Writing>,
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+impl WritingScan {
+    /// Writings followed by placeholders for the notes that could not be read
+    pub fn with_broken(self) -> Vec<Writing> {
+        let broken = self
+            .diagnostics
+            .into_iter()
+            .filter(|d| d.skipped)
+            .map(Writing::broken);
+        self.writings.into_iter().chain(broken).collect()
+    }
 }
 
 pub fn create_writing_list(config: &Config) -> Result<Vec<Writing>, Box<dyn std::error::Error>> {
-    let directory_path = config
-        .get_source_dir()
-        .ok_or("source dir should be set")?;
+    Ok(scan_writings(config)?.writings)
+}
+
+/// Read every note in `source_dir`, collecting parse problems instead of
+/// dropping them silently
+pub fn scan_writings(config: &Config) -> Result<WritingScan, Box<dyn std::error::Error>> {
+    let discovery = Discovery::new(config)?;
     let mut writings: Vec<Writing> = Vec::new();
+    let mut diagnostics = Vec::new();
 
-    for entry in WalkDir::new(&directory_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if entry.file_type().is_file() && entry.path().extension().map_or(false, |e| e == "md") {
-            let entry_path = entry.clone().into_path();
-            if let Ok((frontmatter, _)) =
-                read_markdown_file(&entry_path.as_path().display().to_string())
-            {
-                let title = frontmatter["title"]
-                    .as_str()
-                    .unwrap_or_default()
-                    .to_string();
-                let is_draft = frontmatter["draft"].as_bool().unwrap_or(false);
-                let publish_date = frontmatter["publishDate"].as_str().unwrap_or("");
-                let writing_path = entry_path.as_path().display().to_string();
-                let writing = Writing::new(writing_path, title, is_draft, publish_date);
+    for entry_path in discovery.walk() {
+        match load_writing(config, &entry_path) {
+            Ok(writing) => {
+                diagnostics.extend(writing.diagnostic.clone());
                 writings.push(writing);
             }
+            Err(diagnostic) => diagnostics.push(diagnostic),
         }
     }
+    naming::dedupe_slugs(&mut writings);
+    sort_writings(&mut writings);
+    diagnostics.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(WritingScan {
+        writings,
+        diagnostics,
+    })
+}
+
+/// Read a single note into a `Writing`. Its slug is not yet made unique
+/// against the other notes, see `naming::dedupe_slugs`.
+pub fn load_writing(config: &Config, path: &Path) -> Result<Writing, ParseDiagnostic> {
+    let writing_path = path.display().to_string();
+    let skipped = |line, message| ParseDiagnostic {
+        path: writing_path.clone(),
+        line,
+        message,
+        skipped: true,
+    };
+    let content = fs::read_to_string(path).map_err(|e| skipped(None, format!("Cannot read file: {}", e)))?;
+    let document = Document::parse(&content).map_err(|e| skipped(e.line, e.message))?;
+    let key_map = config.frontmatter_map();
+    let mut frontmatter = document.frontmatter;
+    frontmatter::normalize_source_keys(&mut frontmatter, &key_map);
+
+    let title = frontmatter["title"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let is_draft = frontmatter["draft"].as_bool().unwrap_or(false);
+    let publish_date = match &frontmatter["publishDate"] {
+        serde_yaml::Value::Null => String::new(),
+        serde_yaml::Value::String(date) => date.clone(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+    };
+    let slug = frontmatter["slug"].as_str();
+    let mut writing = Writing::new(writing_path.clone(), title, is_draft, &publish_date, slug);
+    if !publish_date.is_empty() && writing.publish_date.is_none() {
+        let date_keys: Vec<&str> = key_map
+            .source
+            .get("publishDate")
+            .map(String::as_str)
+            .into_iter()
+            .chain(["publishDate"])
+            .collect();
+        writing.diagnostic = Some(ParseDiagnostic {
+            path: writing_path,
+            line: key_line(&content, &date_keys),
+            message: format!("Invalid publishDate '{}', expected YYYY-MM-DD", publish_date),
+            skipped: false,
+        });
+    }
+    Ok(writing)
+}
+
+/// Drafts first, then newest first
+pub fn sort_writings(writings: &mut [Writing]) {
     writings.sort_by(|a, b| {
         a.is_draft
             .cmp(&b.is_draft)
             .reverse()
             .then_with(|| a.publish_date.cmp(&b.publish_date).reverse())
     });
-    Ok(writings)
+}
+
+/// Line of the first frontmatter entry for one of `keys`, in any of the
+/// supported formats
+fn key_line(content: &str, keys: &[&str]) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| {
+            let line = line.trim_start();
+            keys.iter().any(|key| {
+                let rest = line
+                    .strip_prefix(&format!("\"{}\"", key))
+                    .or_else(|| line.strip_prefix(key));
+                rest.is_some_and(|r| r.trim_start().starts_with([':', '=']))
+            })
+        })
+        .map(|index| index + 1)
 }